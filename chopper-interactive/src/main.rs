@@ -78,8 +78,8 @@ pub fn load_distances(filename: &str) -> Result<Distances, Box<dyn Error>> {
 // scoring and penalties
 const MAX_DISTANCE: u32 = 1500;
 const PENALTY_DISTANCE: u32 = 500;
-const PENALTY_CITY91: u32 = 100000;
-pub fn calculate_score(path: &mut Path, cities: &Cities, distances: &Distances) {
+const PENALTY_START_END: u32 = 100000;
+pub fn calculate_score(path: &mut Path, cities: &Cities, distances: &Distances, start: usize, end: Option<usize>) {
     let mut total_distance = 0;
     for i in 0..path.city_indexes.len()-1 {
         total_distance += distances[path.city_indexes[i]][path.city_indexes[i+1]]
@@ -95,13 +95,13 @@ pub fn calculate_score(path: &mut Path, cities: &Cities, distances: &Distances)
 
     // apply penalty -1000 point for each extra kilometer
     let mut penalty = if total_distance <= MAX_DISTANCE { 0 } else { (total_distance - MAX_DISTANCE) * PENALTY_DISTANCE };
-    // apply penalty -100000 for not starting in city 91
-    if path.city_indexes.first() != Some(&91) {
-        penalty += PENALTY_CITY91;
+    // apply penalty -100000 for not starting in the start city
+    if path.city_indexes.first() != Some(&start) {
+        penalty += PENALTY_START_END;
     }
-    // apply penalty -100000 for not finishing in city 91
-    if path.city_indexes.last() != Some(&91) {
-        penalty += PENALTY_CITY91;
+    // apply penalty -100000 for not finishing in the end city, unless the route may end anywhere
+    if end.is_some_and(|end| path.city_indexes.last() != Some(&end)) {
+        penalty += PENALTY_START_END;
     }
     path.score = if total_population > penalty { total_population - penalty} else { 0 };
 }
@@ -113,7 +113,8 @@ pub fn path_to_connections(path: &Path) -> Connections
         result.insert(key, HashSet::new());
     }
 
-    for i in 0..path.city_indexes.len()-2 {
+    // every leg, the last one included: it closes a loop and leads to the end city of an open route
+    for i in 0..path.city_indexes.len()-1 {
         result.entry(path.city_indexes[i]).or_insert_with(HashSet::new).insert(path.city_indexes[i+1]);
         result.entry(path.city_indexes[i+1]).or_insert_with(HashSet::new).insert(path.city_indexes[i]);
     }
@@ -121,12 +122,12 @@ pub fn path_to_connections(path: &Path) -> Connections
     result
 }
 
-pub fn connections_to_path(connections: &Connections) -> Path {
-    // traverse the connections, start in city 91
+pub fn connections_to_path(connections: &Connections, start: usize, end: Option<usize>) -> Path {
+    // traverse the connections from the start city
     let mut visited: HashSet<usize> = HashSet::new();
     let mut indexes: Vec<usize> = Vec::new();
 
-    let mut next_neighbor = Some(91);
+    let mut next_neighbor = Some(start);
     loop {
         if next_neighbor.is_none() {
             break;
@@ -142,13 +143,17 @@ pub fn connections_to_path(connections: &Connections) -> Path {
                     visited.insert(neighbor);
                     next_neighbor = Some(neighbor);
                     break; // to avoid adding two neighbors
-                } else if neighbor == 91 {
-                    indexes.push(neighbor);
                 }
             }
         }
     }
 
+    // a loop is closed when the last city is connected back to the start
+    let last = *indexes.last().unwrap();
+    if end == Some(start) && indexes.len() > 2 && connections.get(&last).is_some_and(|neighbors| neighbors.contains(&start)) {
+        indexes.push(start);
+    }
+
     let result = Path {
         city_indexes: indexes,
         population: 0,
@@ -242,9 +247,45 @@ fn update_city_status(index: usize, connections: &mut Connections, city_statuses
     }
 }
 
+// one city name or index, the way gelnica-chopper takes --start-city and --end-city
+fn parse_city(option: &str, text: &str, cities: &Cities) -> usize {
+    let text = text.trim();
+    let city = match text.parse::<usize>() {
+        Ok(city) => (city < cities.names.len()).then_some(city),
+        Err(_) => cities.names.iter().position(|name| name == text),
+    };
+    city.unwrap_or_else(|| {
+        println!("{}: unknown city '{}'", option, text);
+        std::process::exit(2);
+    })
+}
+
+// [--start-city <city>] [--end-city <city|any>], the same options as in gelnica-chopper
+// the route starts in Gelnica (91) and ends where it starts unless --end-city is given
+fn route_ends(cities: &Cities) -> (usize, Option<usize>) {
+    let args: Vec<String> = std::env::args().collect();
+    let value = |name: &str| {
+        let position = args.iter().position(|arg| arg == name)?;
+        let value = args.get(position + 1);
+        if value.is_none() {
+            println!("{} expects a value", name);
+            std::process::exit(2);
+        }
+        value
+    };
+    let start = value("--start-city").map_or(91, |start| parse_city("--start-city", start, cities));
+    let end = match value("--end-city").map(String::as_str) {
+        None => Some(start),
+        Some("any") => None,
+        Some(end) => Some(parse_city("--end-city", end, cities)),
+    };
+    (start, end)
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
     let cities = load_cities("cities.csv").unwrap();
+    let (start, end) = route_ends(&cities);
     println!("{:?}", cities.populations);
 
     let distances = load_distances("city_distance_matrix.csv").unwrap();
@@ -265,7 +306,7 @@ async fn main() {
 
     // preload path
     let preloaded_path = Path {
-        city_indexes: vec![91, 2, 91, 59, 20, 57, 64, 88, 33, 18, 81, 31, 14, 90, 32, 84, 1, 71, 53, 119, 25, 68, 23, 76, 50, 5, 85, 11, 56, 82, 69, 39, 44, 10, 28, 42, 49, 72, 16, 80, 61, 17, 77, 13, 66, 65, 73, 30, 60, 103, 0, 79, 45, 87, 37, 75, 52, 6, 47, 27, 3, 29, 22, 34, 62, 51, 55, 67, 35, 8, 58, 24, 43, 12, 63, 4, 46, 83, 26, 78, 70, 41, 7, 21, 19, 38, 54, 40, 9, 89, 48, 86, 36, 15, 74, 91],
+        city_indexes: vec![91, 2, 59, 20, 57, 64, 88, 33, 18, 81, 31, 14, 90, 32, 84, 1, 71, 53, 119, 25, 68, 23, 76, 50, 5, 85, 11, 56, 82, 69, 39, 44, 10, 28, 42, 49, 72, 16, 80, 61, 17, 77, 13, 66, 65, 73, 30, 60, 103, 0, 79, 45, 87, 37, 75, 52, 6, 47, 27, 3, 29, 22, 34, 62, 51, 55, 67, 35, 8, 58, 24, 43, 12, 63, 4, 46, 83, 26, 78, 70, 41, 7, 21, 19, 38, 54, 40, 9, 89, 48, 86, 36, 15, 74, 91],
         //city_indexes: vec![91, 2, 59, 20, 57, 64, 88, 33, 18, 81, 31, 14, 90, 32, 84, 1, 71, 53, 25, 68, 23, 76, 50, 5, 85, 11, 56, 82, 69, 39, 44, 10, 28, 42, 49, 72, 92, 16, 80, 61, 17, 77, 13, 66, 65, 73, 30, 60, 0, 79, 45, 87, 37, 75, 52, 6, 47, 27, 3, 29, 22, 34, 62, 51, 55, 67, 35, 8, 58, 24, 43, 12, 63, 4, 46, 117, 83, 26, 78, 70, 41, 100, 7, 21, 19, 38, 54, 40, 9, 89, 48, 86, 36, 15, 74, 91],
         population: 0,
         distance: 0,
@@ -309,8 +350,8 @@ async fn main() {
                     selected_buttons.clear();
 
                     // print the current score
-                    let mut path: Path = connections_to_path(&connections);
-                    calculate_score(&mut path, &cities, &distances);
+                    let mut path: Path = connections_to_path(&connections, start, end);
+                    calculate_score(&mut path, &cities, &distances, start, end);
                     println!("{}", path);
                 }
             }
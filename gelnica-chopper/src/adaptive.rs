@@ -0,0 +1,83 @@
+use std::fmt;
+
+use crate::params::GaParams;
+
+// mutation operators in the order mutate applies them
+pub const OPERATORS: [&str; 5] = ["first", "last", "add", "remove", "reorder"];
+
+// adaptive pursuit: the operator with the best recent success rate is pushed towards RATE_MAX, the others towards RATE_MIN
+// RATE_MIN keeps every operator alive, so one that starts to pay off later can win again
+const RATE_MIN: f64 = 2.0;
+const RATE_MAX: f64 = 80.0;
+const LEARNING_RATE: f64 = 0.3; // how fast the success rate estimates follow new results
+const PURSUIT_RATE: f64 = 0.2;  // how fast the chances follow the best operator
+pub const WINDOW: usize = 100;  // iterations between two updates of the chances
+pub const LOG_INTERVAL: usize = 5000;
+
+// mutation chances adapted during the run, by how often each drawn operator made a mutated path better than its parent
+#[derive(Clone, Debug)]
+pub struct AdaptiveRates {
+    chances: [f64; 5],  // percent, as the MUTATION_CHANCE_* constants
+    quality: [f64; 5],  // estimated share of improving offspring
+    attempts: [u32; 5], // since the last update
+    successes: [u32; 5],
+    pub iterations: usize,
+}
+
+impl fmt::Display for AdaptiveRates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rates: Vec<String> = OPERATORS.iter().zip(self.chances).zip(self.quality)
+            .map(|((operator, chance), quality)| format!("{}={:.1}% ({:.3})", operator, chance, quality))
+            .collect();
+        write!(f, "{}", rates.join(", "))
+    }
+}
+
+impl AdaptiveRates {
+    // starts from the configured chances
+    pub fn new(params: &GaParams) -> AdaptiveRates {
+        AdaptiveRates {
+            chances: params.chances().map(f64::from),
+            quality: [0.0; 5],
+            attempts: [0; 5],
+            successes: [0; 5],
+            iterations: 0,
+        }
+    }
+
+    pub fn chances(&self) -> [u8; 5] {
+        self.chances.map(|chance| chance.round() as u8)
+    }
+
+    // one mutated path, applied as returned by calculations::mutate_with_chances
+    // a drawn operator that could not change the path counts as a failure, so operators that rarely apply do not win
+    pub fn record(&mut self, applied: [Option<bool>; 5], improved: bool) {
+        for (operator, changed) in applied.iter().enumerate() {
+            if let Some(changed) = changed {
+                self.attempts[operator] += 1;
+                self.successes[operator] += u32::from(*changed && improved);
+            }
+        }
+    }
+
+    // counts one iteration, every WINDOW iterations the recorded results move the chances
+    pub fn next_iteration(&mut self) {
+        self.iterations += 1;
+        if !self.iterations.is_multiple_of(WINDOW) {
+            return;
+        }
+        for operator in 0..OPERATORS.len() {
+            if self.attempts[operator] > 0 {
+                let reward = f64::from(self.successes[operator]) / f64::from(self.attempts[operator]);
+                self.quality[operator] += LEARNING_RATE * (reward - self.quality[operator]);
+            }
+        }
+        let best = (0..OPERATORS.len()).max_by(|&a, &b| self.quality[a].total_cmp(&self.quality[b])).unwrap();
+        for operator in 0..OPERATORS.len() {
+            let target = if operator == best { RATE_MAX } else { RATE_MIN };
+            self.chances[operator] += PURSUIT_RATE * (target - self.chances[operator]);
+        }
+        self.attempts = [0; 5];
+        self.successes = [0; 5];
+    }
+}
@@ -0,0 +1,83 @@
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::calculations::Path;
+
+pub const ARCHIVE_FILE: &str = "archive.jsonl";
+
+// one archived solution, stored as a single JSON line
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: usize,
+    pub timestamp: u64, // seconds since the unix epoch when the route was archived
+    pub source: String, // where the route came from, e.g. "results.txt:12"
+    pub seed: Option<u64>,
+    pub config: Option<String>,
+    pub city_indexes: Vec<usize>,
+    pub score: u32,
+    pub population: u32,
+    pub distance: u32,
+}
+
+impl Entry {
+    pub fn path(&self) -> Path {
+        Path {
+            city_indexes: self.city_indexes.clone(),
+            population: self.population,
+            distance: self.distance,
+            score: self.score,
+        }
+    }
+}
+
+// missing archive is the same as an empty one
+pub fn load(filename: &str) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let file = match std::fs::File::open(filename) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    let mut entries = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Entry = serde_json::from_str(&line).map_err(|error| format!("{}, line {}: {}", filename, index + 1, error))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+// appends the path unless the very same route is archived already, returns the new entry
+pub fn add(filename: &str, path: &Path, source: &str, seed: Option<u64>, config: Option<String>) -> Result<Option<Entry>, Box<dyn Error>> {
+    let entries = load(filename)?;
+    if entries.iter().any(|entry| entry.city_indexes == path.city_indexes) {
+        return Ok(None);
+    }
+    let entry = Entry {
+        id: entries.iter().map(|entry| entry.id + 1).max().unwrap_or(1),
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        source: source.to_string(),
+        seed,
+        config,
+        city_indexes: path.city_indexes.clone(),
+        score: path.score,
+        population: path.population,
+        distance: path.distance,
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(filename)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(Some(entry))
+}
+
+// best first
+pub fn best(entries: &[Entry], count: usize) -> Vec<&Entry> {
+    let mut sorted: Vec<&Entry> = entries.iter().collect();
+    sorted.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+    sorted.truncate(count);
+    sorted
+}
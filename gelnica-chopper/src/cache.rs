@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::calculations::{Config, Path};
+
+// routes kept before the older half is dropped
+pub const CACHE_CAPACITY: usize = 4096;
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+#[derive(Clone, Debug)]
+struct Entry {
+    stops: Vec<usize>, // compared on every hit, so that a route sharing the hash of another one is a miss
+    population: u32,
+    distance: u32,
+    score: u32,
+}
+
+// FNV-1a over the stops in their order
+// a route is not the same as its reverse here, open routes, opening hours and day splits depend on the direction
+fn route_hash(stops: &[usize]) -> u64 {
+    stops.iter().fold(FNV_OFFSET, |hash, &city| (hash ^ city as u64).wrapping_mul(FNV_PRIME))
+}
+
+// score components of recently scored routes, keyed by the route hash
+// bounded by keeping two halves: when the current one is full it replaces the previous one
+#[derive(Clone, Debug, Default)]
+pub struct ScoreCache {
+    current: HashMap<u64, Entry>,
+    previous: HashMap<u64, Entry>,
+    config: String, // scores are only valid for the config they were computed with
+    disabled: bool, // Config::score_cache is off, nothing is stored or found
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl fmt::Display for ScoreCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.disabled {
+            return write!(f, "off");
+        }
+        let lookups = self.hits + self.misses;
+        let rate = if lookups == 0 { 0.0 } else { self.hits as f64 * 100.0 / lookups as f64 };
+        write!(f, "{:.1}% hits of {} lookups, {} routes stored", rate, lookups, self.current.len() + self.previous.len())
+    }
+}
+
+impl ScoreCache {
+    // forgets every score when the config differs from the one they were computed with
+    pub fn use_config(&mut self, config: &Config) {
+        self.disabled = !config.score_cache;
+        let config = config.to_string();
+        if config != self.config {
+            self.current.clear();
+            self.previous.clear();
+            self.config = config;
+        }
+    }
+
+    // fills the score components of the path, false when it is not cached
+    pub fn lookup(&mut self, path: &mut Path) -> bool {
+        if self.disabled {
+            return false;
+        }
+        let hash = route_hash(&path.city_indexes);
+        let (entry, in_current) = match self.current.get(&hash) {
+            Some(entry) => (Some(entry), true),
+            None => (self.previous.get(&hash), false),
+        };
+        let Some(entry) = entry.filter(|entry| entry.stops == path.city_indexes) else {
+            self.misses += 1;
+            return false;
+        };
+        self.hits += 1;
+        path.population = entry.population;
+        path.distance = entry.distance;
+        path.score = entry.score;
+        if !in_current {
+            self.store(path);
+        }
+        true
+    }
+
+    pub fn store(&mut self, path: &Path) {
+        if self.disabled {
+            return;
+        }
+        if self.current.len() >= CACHE_CAPACITY {
+            self.previous = std::mem::take(&mut self.current);
+        }
+        let entry = Entry { stops: path.city_indexes.clone(), population: path.population, distance: path.distance, score: path.score };
+        self.current.insert(route_hash(&path.city_indexes), entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(stops: &[usize], score: u32) -> Path {
+        Path { city_indexes: stops.to_vec(), population: score * 2, distance: score / 2, score }
+    }
+
+    #[test]
+    fn a_stored_route_is_found_with_its_scores() {
+        let mut cache = ScoreCache::default();
+        cache.store(&path(&[91, 1, 2, 91], 1000));
+        let mut found = path(&[91, 1, 2, 91], 0);
+        assert!(cache.lookup(&mut found));
+        assert_eq!((found.population, found.distance, found.score), (2000, 500, 1000));
+        // the reverse is another route
+        assert!(!cache.lookup(&mut path(&[91, 2, 1, 91], 0)));
+        assert_eq!((cache.hits, cache.misses), (1, 1));
+    }
+
+    #[test]
+    fn a_route_sharing_the_hash_of_another_one_is_a_miss() {
+        let mut cache = ScoreCache::default();
+        cache.store(&path(&[91, 1, 2, 91], 1000));
+        // pretend the two routes collide: move the entry under the hash of the other route
+        let entry = cache.current.remove(&route_hash(&[91, 1, 2, 91])).unwrap();
+        cache.current.insert(route_hash(&[91, 3, 91]), entry);
+        let mut other = path(&[91, 3, 91], 7);
+        assert!(!cache.lookup(&mut other));
+        assert_eq!(other.score, 7);
+    }
+
+    #[test]
+    fn a_disabled_cache_finds_nothing() {
+        let mut cache = ScoreCache::default();
+        cache.use_config(&Config { score_cache: false, ..Config::default() });
+        cache.store(&path(&[91, 1, 2, 91], 1000));
+        assert!(!cache.lookup(&mut path(&[91, 1, 2, 91], 0)));
+        assert_eq!(cache.to_string(), "off");
+    }
+}
@@ -1,35 +1,66 @@
 use std::collections::HashSet;
-use std::error::Error;
 use std::fmt;
-use std::thread;
-use csv::Reader;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
 use rand::Rng;
-use rand::rngs::ThreadRng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 
+use crate::adaptive::{self, AdaptiveRates};
+use crate::cache::ScoreCache;
+use crate::construct::{self, Initializer, InitializerMix};
+use crate::coverage;
+use crate::diversity;
+use crate::moves::{self, Evaluation, Move};
+use crate::params::{Crossover, GaParams};
+use crate::roads::DriveThrough;
+use crate::timing::TimeModel;
+use crate::trip;
+use crate::value::ValueModel;
+
 // constants
 pub const ITERATION_COUNT: u32 = 50000;
-const GENERATION_SIZE: usize = 100;
-const BOUNDARY_ELITE: usize = 20;
-const BOUNDARY_MUTATE: usize = 40;
-const BOUNDARY_RANDOM: usize = 50;
+pub const GENERATION_SIZE: usize = 100;
+pub const BOUNDARY_ELITE: usize = 20;
+pub const BOUNDARY_MUTATE: usize = 40;
+pub const BOUNDARY_RANDOM: usize = 50;
+pub const HOME_CITY: usize = 91; // Gelnica
+// cities of the reference cities.csv that are penalized when visited, by index
+const BAD_CITIES: [RangeInclusive<usize>; 3] = [92..=102, 104..=118, 120..=138];
 const MAX_DISTANCE: u32 = 1500;
-const PENALTY_DISTANCE: u32 = 500;
-const PENALTY_CITY91: u32 = 100000;
-const PENALTY_BADCITY: u32 = 10000;
-
-const MUTATION_CHANCE_FIRSTCITY: u8 = 5;
-const MUTATION_CHANCE_LASTCITY: u8 = 5;
-const MUTATION_CHANCE_ADDONE: u8 = 50;
-const MUTATION_CHANCE_REMOVEONE: u8 = 50;
-const MUTATION_CHANCE_REORDERONE: u8 = 20;
+pub const PENALTY_DISTANCE: u32 = 500;
+const PENALTY_MINUTE: u32 = 5000;
+const PENALTY_LATE: u32 = 100000;
+pub const PENALTY_START_END: u32 = 100000;
+pub const PENALTY_BADCITY: u32 = 10000;
+pub const PENALTY_REQUIRED: u32 = 100000;
+pub const PENALTY_AVOIDED: u32 = 100000;
+const PENALTY_COVERAGE: u32 = 100000;
+
+pub const MUTATION_CHANCE_FIRSTCITY: u8 = 5;
+pub const MUTATION_CHANCE_LASTCITY: u8 = 5;
+pub const MUTATION_CHANCE_ADDONE: u8 = 50;
+pub const MUTATION_CHANCE_REMOVEONE: u8 = 50;
+pub const MUTATION_CHANCE_REORDERONE: u8 = 20;
 
 // custom types
 #[derive(Clone)]
 pub struct Cities {
     pub names: Vec<String>,
     pub populations: Vec<u32>,
-    pub coords: Vec<(f32, f32)>
+    pub coords: Vec<(f32, f32)>,
+    pub overnight: Vec<bool>, // the city can be the end of a day of a multi-day trip
+    pub values: Vec<Option<u32>>, // custom value of the city, None without a value column
+    pub regions: Grouping,
+    pub districts: Grouping,
+}
+
+// cities grouped by an optional column of cities.csv, e.g. regions or districts
+#[derive(Clone, Default)]
+pub struct Grouping {
+    pub names: Vec<String>,
+    pub of_city: Vec<Option<usize>>, // index into names, None when the city has no group
 }
 
 #[derive(Clone)]
@@ -50,44 +81,165 @@ impl fmt::Display for Path {
 }
 
 pub type Distances = Vec<Vec<u32>>;
-pub type Generation = [Path; GENERATION_SIZE];
-
-pub fn load_cities(filename: &str) -> Result<Cities, Box<dyn Error>> {
-    let mut rdr = Reader::from_path(filename)?; // try to load the file, return Error if something goes wrong
-    let mut names = Vec::new();
-    let mut populations = Vec::new();
-    let mut coords = Vec::new();
-    for (_index, result) in rdr.records().enumerate() {
-        let record = result?;
-        names.push(record[1].to_string());
-        let population: u32 = record[2].parse()?;
-        populations.push(population);
-
-        let coord1: f32 = record[3].parse()?;
-        let coord2: f32 = record[4].parse()?;
-        coords.push((coord1, coord2));
-    }
-    Ok(Cities { names: names, populations: populations, coords: coords})
+// paths ordered by score after every iteration, and what the run has learned so far
+pub struct Generation {
+    pub paths: Vec<Path>,
+    pub rates: Option<AdaptiveRates>, // adapted mutation chances, None when the configured ones are used
+    pub cache: ScoreCache,            // the elite and other unchanged routes are not scored again
+}
+
+impl Generation {
+    pub fn new(paths: Vec<Path>, config: &Config) -> Generation {
+        Generation { paths, rates: config.adaptive_mutation.then(|| AdaptiveRates::new(&config.ga)), cache: ScoreCache::default() }
+    }
+}
+
+// run-time settings, the default reproduces the rules given by the constants above
+#[derive(Clone)]
+pub struct Config {
+    // where the route starts and ends, None ends anywhere
+    pub start: usize,
+    pub end: Option<usize>,
+    // km limit of the route, None when only the time or the daily limits count
+    pub max_distance: Option<u32>,
+    // cities lying on the road of a leg count as visited (requires a road network)
+    pub drive_through: Option<Arc<DriveThrough>>,
+    // known routes put into the first generation
+    pub seed_routes: Vec<Vec<usize>>,
+    // fill the rest of the first generation with mutated seed routes instead of random ones
+    pub seed_mutations: bool,
+    // how new paths are created, in the first generation and in the random slice of every generation
+    pub initializers: InitializerMix,
+    // km limit of every day of a multi-day trip, empty for a single day limited by max_distance
+    pub day_limits: Vec<u32>,
+    // driving and visiting times, the time budget is checked together with the km limits
+    pub time: Option<Arc<TimeModel>>,
+    // cities that have to be in the route and cities that must never be
+    pub required: Vec<usize>,
+    pub avoided: Vec<usize>,
+    // cities penalized when visited anywhere but at the end, sorted, the ones of the reference dataset by default
+    pub bad_cities: Vec<usize>,
+    // what the visited cities are worth, plus a bonus for every region and district with a visited city
+    pub value: ValueModel,
+    pub region_bonus: u32,
+    pub district_bonus: u32,
+    // at least this many cities in every region (0 for no minimum), at most this many in every district
+    pub min_per_region: u32,
+    pub max_per_district: Option<u32>,
+    // generation size, slices of the generation, mutation chances and crossover
+    pub ga: GaParams,
+    // adapt the mutation chances during the run instead of keeping the configured ones
+    pub adaptive_mutation: bool,
+    // vary copies in the elite and replace the crossover slots by deterministic crowding
+    pub diversity: bool,
+    // improve the best path of every run by hill climbing over single moves
+    pub local_search: bool,
+    // reuse the scores of routes seen recently, off scores every route of every iteration again (for comparing speed)
+    pub score_cache: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            start: HOME_CITY,
+            end: Some(HOME_CITY),
+            max_distance: Some(MAX_DISTANCE),
+            drive_through: None,
+            seed_routes: Vec::new(),
+            seed_mutations: false,
+            initializers: InitializerMix::default(),
+            day_limits: Vec::new(),
+            time: None,
+            required: Vec::new(),
+            avoided: Vec::new(),
+            bad_cities: default_bad_cities(),
+            value: ValueModel::Raw,
+            region_bonus: 0,
+            district_bonus: 0,
+            min_per_region: 0,
+            max_per_district: None,
+            ga: GaParams::default(),
+            adaptive_mutation: false,
+            diversity: false,
+            local_search: false,
+            score_cache: true,
+        }
+    }
+}
+
+impl Config {
+    pub fn is_bad_city(&self, city: usize) -> bool {
+        self.bad_cities.binary_search(&city).is_ok()
+    }
+
+    // km available for the whole route
+    pub fn budget(&self) -> Option<u32> {
+        if self.day_limits.is_empty() { self.max_distance } else { Some(self.day_limits.iter().sum()) }
+    }
+
+    // rules for the constructive initializers
+    pub fn construct_rules(&self) -> construct::Rules<'_> {
+        construct::Rules { start: self.start, end: self.end, km: self.budget(), time: self.time.as_deref(), avoid: &self.avoided, bad: &self.bad_cities }
+    }
+}
+
+// key=value pairs, printed with every run and stored in the archive
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |values: &[usize]| -> String {
+            let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+            if values.is_empty() { "-".to_string() } else { values.join(",") }
+        };
+        let day_limits: Vec<usize> = self.day_limits.iter().map(|&limit| limit as usize).collect();
+        write!(f, "start={} end={} max_distance={} drive_through={} seed_routes={} seed_mutations={} initializers={} days={} time={} required={} avoided={} bad_cities={} value={} region_bonus={} district_bonus={} min_per_region={} max_per_district={} ga={} adaptive_mutation={} diversity={} local_search={} score_cache={}",
+            self.start, self.end.map_or("any".to_string(), |end| end.to_string()),
+            self.max_distance.map_or("-".to_string(), |km| km.to_string()), self.drive_through.is_some(), self.seed_routes.len(),
+            self.seed_mutations, self.initializers, list(&day_limits),
+            self.time.as_ref().map_or("-".to_string(), |time| time.to_string()), list(&self.required), list(&self.avoided),
+            if self.bad_cities == default_bad_cities() { "default".to_string() } else { list(&self.bad_cities) },
+            self.value, self.region_bonus, self.district_bonus, self.min_per_region,
+            self.max_per_district.map_or("-".to_string(), |max| max.to_string()), self.ga,
+            self.adaptive_mutation, self.diversity, self.local_search, self.score_cache)
+    }
 }
 
-pub fn load_distances(filename: &str) -> Result<Distances, Box<dyn Error>> {
-    let mut rdr = Reader::from_path(filename)?;
-    let mut distances = Vec::new();
-    for result in rdr.records() {
-        let record = result?;
-        let row: Vec<u32> = record.iter()
-                .skip(1) // Skip the first column
-                .map(|value| value.parse().unwrap()) // parse all other columns as numbers
-                .collect::<Vec<u32>>(); // put results into vector
-        distances.push(row);
-    }
-    Ok(distances)
+pub fn default_bad_cities() -> Vec<usize> {
+    BAD_CITIES.iter().flat_map(|cities| cities.clone()).collect()
 }
 
-fn generate_random_path_with_rng(rng: &mut ThreadRng) -> Path {
+// cities picked up for free by driving through them, in the order they are passed
+// bad and avoided cities are never picked up, passing through does not count as a visit the user asked to skip
+pub fn driven_through(path: &Path, config: &Config) -> Vec<usize> {
+    let Some(drive_through) = &config.drive_through else {
+        return Vec::new();
+    };
+    let stops: HashSet<usize> = path.city_indexes.iter().copied().collect();
+    let mut result = Vec::new();
+    for leg in path.city_indexes.windows(2) {
+        for &city in &drive_through[leg[0]][leg[1]] {
+            if !stops.contains(&city) && !config.is_bad_city(city) && !config.avoided.contains(&city) && !result.contains(&city) {
+                result.push(city);
+            }
+        }
+    }
+    result
+}
+
+// reads a route typed by hand or copied from the output, e.g. "91, 2, 59, 91" or "Score: 1, ..., Cities: [91, 2, 91]"
+pub fn parse_city_indexes(text: &str) -> Vec<usize> {
+    let text = match text.find("Cities:") {
+        Some(position) => &text[position + "Cities:".len()..],
+        None => text,
+    };
+    text.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|number| number.parse().ok())
+        .collect()
+}
+
+fn generate_random_path_with_rng(rng: &mut StdRng, city_count: usize, config: &Config) -> Path {
     let size = 10; // Change this to the desired size of the vector
-    let range = 0..=138; // 138 is included due to '=' sign
-    let random_numbers: Vec<usize> = (0..size).map(|_| rng.gen_range(range.clone())).collect();
+    let allowed: Vec<usize> = (0..city_count).filter(|city| !config.avoided.contains(city)).collect();
+    let random_numbers: Vec<usize> = (0..size).map(|_| allowed[rng.gen_range(0..allowed.len())]).collect();
 
     Path {
         city_indexes: random_numbers.clone(),
@@ -97,121 +249,289 @@ fn generate_random_path_with_rng(rng: &mut ThreadRng) -> Path {
     }
 }
 
-pub fn generate_random_path() -> Path {
-    let mut rng = rand::thread_rng();
-    generate_random_path_with_rng(&mut rng)
+// position where inserting the city makes the route the shortest, and the km it adds
+// between two stops, so that the start and the end stay where they are
+// a route that may end anywhere can also be extended at its end
+pub fn cheapest_insertion(stops: &[usize], city: usize, distances: &Distances, config: &Config) -> (usize, i64) {
+    let last = if config.end.is_none() { stops.len() } else { stops.len().saturating_sub(1) };
+    (1..=last)
+        .map(|i| match stops.get(i) {
+            Some(&after) => {
+                let before = stops[i - 1];
+                (i, i64::from(distances[before][city]) + i64::from(distances[city][after]) - i64::from(distances[before][after]))
+            }
+            None => (i, i64::from(distances[stops[i - 1]][city])),
+        })
+        .min_by_key(|&(_, cost)| cost)
+        .unwrap_or((stops.len(), 0))
+}
+
+// puts every missing required city where it makes the route the shortest
+pub fn insert_required(path: &mut Path, distances: &Distances, config: &Config) {
+    for &city in &config.required {
+        if path.city_indexes.contains(&city) {
+            continue;
+        }
+        let (position, _) = cheapest_insertion(&path.city_indexes, city, distances, config);
+        path.city_indexes.insert(position, city);
+    }
+}
+
+// fixes what the operators can break cheaply: missing required cities and the region and district limits
+pub fn repair(path: &mut Path, cities: &Cities, distances: &Distances, config: &Config) {
+    insert_required(path, distances, config);
+    coverage::repair(path, cities, distances, config);
+}
+
+// new path made by one of the initializers, picked according to the configured mix
+fn generate_path(cities: &Cities, distances: &Distances, config: &Config, rng: &mut StdRng) -> Path {
+    let mut path = generate_path_unchecked(cities, distances, config, rng);
+    repair(&mut path, cities, distances, config);
+    path
+}
+
+fn generate_path_unchecked(cities: &Cities, distances: &Distances, config: &Config, rng: &mut StdRng) -> Path {
+    let city_indexes = match config.initializers.pick(rng) {
+        Initializer::Random => return generate_random_path_with_rng(rng, cities.names.len(), config),
+        Initializer::NearestNeighbour => construct::nearest_neighbour(cities, distances, config.construct_rules(), rng),
+        Initializer::Greedy => construct::randomized_greedy(cities, distances, config.construct_rules(), rng),
+        Initializer::Sweep => construct::sweep(cities, distances, config.construct_rules(), rng),
+    };
+    Path {
+        city_indexes,
+        population: 0,
+        distance: 0,
+        score: 0,
+    }
 }
 
-pub fn calculate_score(path: &mut Path, cities: &Cities, distances: &Distances) {
+// seed routes first, the rest is either mutated copies of the seeds or made by the initializers
+pub fn initial_generation(cities: &Cities, distances: &Distances, config: &Config, rng: &mut StdRng) -> Generation {
+    let seeds: Vec<Path> = config.seed_routes.iter().map(|city_indexes| {
+        let mut path = Path {
+            city_indexes: city_indexes.clone(),
+            population: 0,
+            distance: 0,
+            score: 0,
+        };
+        repair(&mut path, cities, distances, config);
+        path
+    }).collect();
+    let paths = (0..config.ga.generation_size).map(|i| {
+        if i < seeds.len() {
+            seeds[i].clone()
+        } else if config.seed_mutations && !seeds.is_empty() {
+            mutate(&seeds[i % seeds.len()], cities, config, rng)
+        } else {
+            generate_path(cities, distances, config, rng)
+        }
+    }).collect();
+    Generation::new(paths, config)
+}
+
+pub fn calculate_score(path: &mut Path, cities: &Cities, distances: &Distances, config: &Config) {
     let mut total_distance = 0;
     for i in 0..path.city_indexes.len()-1 {
         total_distance += distances[path.city_indexes[i]][path.city_indexes[i+1]]
     }
     path.distance = total_distance;
 
-    let unique_cities: HashSet<usize> = path.city_indexes.iter().copied().collect(); // Convert &usize to usize
+    let unique_cities = visited_cities(path, config);
     let mut total_population = 0;
-    for item in unique_cities {
-        total_population += cities.populations[item]
+    for item in &unique_cities {
+        total_population += cities.populations[*item]
     }
     path.population = total_population;
+    let total_value = route_value(&unique_cities, cities, config);
 
-    // apply penalty -1000 point for each extra kilometer
-    let mut penalty = if total_distance <= MAX_DISTANCE { 0 } else { (total_distance - MAX_DISTANCE) * PENALTY_DISTANCE };
-    // apply penalty -100000 for not starting in city 91
-    if path.city_indexes.first() != Some(&91) {
-        penalty += PENALTY_CITY91;
+    // apply penalty -1000 point for each extra kilometer, on a multi-day trip for each kilometer over the daily limits
+    let extra_distance = if config.day_limits.is_empty() {
+        config.max_distance.map_or(0, |max_distance| total_distance.saturating_sub(max_distance))
+    } else {
+        trip::excess(&trip::split_days(path, cities, distances, &config.day_limits))
+    };
+    let mut penalty = extra_distance * PENALTY_DISTANCE;
+    // and for each minute over the time budget
+    if let Some(time) = &config.time {
+        let visits = time.visits(&path.city_indexes, distances);
+        if let (Some(budget), Some(end)) = (time.budget, visits.last()) {
+            penalty += end.arrival.saturating_sub(budget) * PENALTY_MINUTE;
+        }
+        // a city reached after it closed is not allowed: it is worth nothing and costs more than skipping it,
+        // plus a penalty growing with the delay so that the less late of two broken routes still wins
+        for (&city, visit) in path.city_indexes.iter().zip(&visits).filter(|(_, visit)| visit.late > 0) {
+            penalty += config.value.city_value(cities, city) + PENALTY_LATE + visit.late * PENALTY_MINUTE;
+        }
     }
-    // apply penalty -100000 for not finishing in city 91
-    if path.city_indexes.last() != Some(&91) {
-        penalty += PENALTY_CITY91;
+    penalty += route_penalty(path, config);
+    // and for every city missing in a region or over the limit of a district
+    penalty += coverage::violations(&unique_cities, cities, config) * PENALTY_COVERAGE;
+
+    path.score = total_value.saturating_sub(penalty);
+}
+
+// stops and the cities driven through on the way
+pub fn visited_cities(path: &Path, config: &Config) -> HashSet<usize> {
+    let mut unique_cities: HashSet<usize> = path.city_indexes.iter().copied().collect(); // Convert &usize to usize
+    unique_cities.extend(driven_through(path, config));
+    unique_cities
+}
+
+// worth of the visited cities in the configured value model, plus the coverage bonuses
+pub fn route_value(visited: &HashSet<usize>, cities: &Cities, config: &Config) -> u32 {
+    let mut total = config.value.route_value(cities, visited);
+    if config.region_bonus > 0 {
+        total += coverage::covered(&cities.regions, visited) as u32 * config.region_bonus;
     }
-    // apply penalty for cities
-    let penalty_cities =
-    for i in 0..path.city_indexes.len()-1 {
-        let mut penalties = 0;
-        match path.city_indexes[i] {
-            92..=102 => penalties += 1,
-            103 => {},
-            104..=118 => penalties += 1,
-            119 => {},
-            120..=138 => penalties += 1,
-            _ => {}
-        };
-        penalty += penalties * PENALTY_BADCITY;
-    };
+    if config.district_bonus > 0 {
+        total += coverage::covered(&cities.districts, visited) as u32 * config.district_bonus;
+    }
+    total
+}
 
-    path.score = if total_population > penalty { total_population - penalty} else { 0 };
+// penalties that do not depend on the driven distance, 0 for a valid route
+pub fn route_penalty(path: &Path, config: &Config) -> u32 {
+    let mut penalty = 0;
+    // apply penalty -100000 for not starting in the start city (91 by default)
+    if path.city_indexes.first() != Some(&config.start) {
+        penalty += PENALTY_START_END;
+    }
+    // apply penalty -100000 for not finishing in the end city, unless the route may end anywhere
+    if config.end.is_some_and(|end| path.city_indexes.last() != Some(&end)) {
+        penalty += PENALTY_START_END;
+    }
+    // apply penalty for cities, unless they were asked for
+    for i in 0..path.city_indexes.len()-1 {
+        if config.is_bad_city(path.city_indexes[i]) && !config.required.contains(&path.city_indexes[i]) {
+            penalty += PENALTY_BADCITY;
+        }
+    }
+    // apply penalty for every required city that is missing and every visit of an avoided one
+    for city in &config.required {
+        if !path.city_indexes.contains(city) {
+            penalty += PENALTY_REQUIRED;
+        }
+    }
+    for city in &path.city_indexes {
+        if config.avoided.contains(city) {
+            penalty += PENALTY_AVOIDED;
+        }
+    }
+    penalty
 }
 
-pub fn calculate_scores(generation: &mut Generation, cities: &Cities, distances: &Distances) {
-    for path in generation.iter_mut() {
-        calculate_score(path, cities, distances);
+pub fn calculate_scores(generation: &mut Generation, cities: &Cities, distances: &Distances, config: &Config) {
+    for path in generation.paths.iter_mut() {
+        if !generation.cache.lookup(path) {
+            calculate_score(path, cities, distances, config);
+            generation.cache.store(path);
+        }
     }
 
-    generation.sort_by_key(|path| std::cmp::Reverse(path.score));
+    generation.paths.sort_by_key(|path| std::cmp::Reverse(path.score));
+}
+
+// never adds an avoided city or one over its district limit, never removes a required one or the last ones of a region
+pub fn mutate(parent: &Path, cities: &Cities, config: &Config, rng: &mut StdRng) -> Path {
+    mutate_with_chances(parent, cities, config, config.ga.chances(), None, rng).0
 }
 
-fn mutate(parent: &Path, rng: &mut ThreadRng) -> Path {
+// mutate with the chances in the order of adaptive::OPERATORS, also tells which operators were drawn
+// and whether they changed the path, None for an operator that was not drawn
+// with an evaluation of the parent the mutations are applied through it, so that it scores the offspring
+pub fn mutate_with_chances(parent: &Path, cities: &Cities, config: &Config, chances: [u8; 5], mut evaluation: Option<&mut Evaluation>, rng: &mut StdRng) -> (Path, [Option<bool>; 5]) {
     let mut offspring = parent.clone();
+    let mut applied = [None; 5];
+    let [chance_firstcity, chance_lastcity, chance_addone, chance_removeone, chance_reorderone] = chances;
 
     if offspring.city_indexes.len() < 2 {
-        return offspring; // Nothing to swap if the vector has fewer than 2 elements
+        return (offspring, applied); // Nothing to swap if the vector has fewer than 2 elements
     }
+    let mut make = |mv: Move, stops: &mut Vec<usize>| match evaluation.as_deref_mut() {
+        Some(evaluation) => evaluation.apply(&mv, stops),
+        None => mv.apply(stops),
+    };
 
     // apply mutations with a random chance
     let mut random_chance: u8 = rng.gen_range(0..100);
-    if random_chance < MUTATION_CHANCE_FIRSTCITY {
-        if parent.city_indexes.first() != Some(&91) {
-            offspring.city_indexes.insert(0, 91);
+    if random_chance < chance_firstcity {
+        applied[0] = Some(parent.city_indexes.first() != Some(&config.start));
+        if parent.city_indexes.first() != Some(&config.start) {
+            make(Move::Insert { position: 0, city: config.start }, &mut offspring.city_indexes);
         }
     }
 
     random_chance = rng.gen_range(0..100);
-    if random_chance < MUTATION_CHANCE_LASTCITY {
-        if parent.city_indexes.last() != Some(&91) {
-            offspring.city_indexes.push(91);
+    if let Some(end) = config.end {
+        if random_chance < chance_lastcity {
+            applied[1] = Some(parent.city_indexes.last() != Some(&end));
+            if parent.city_indexes.last() != Some(&end) {
+                make(Move::Insert { position: offspring.city_indexes.len(), city: end }, &mut offspring.city_indexes);
+            }
         }
     }
 
     random_chance = rng.gen_range(0..100);
-    if random_chance < MUTATION_CHANCE_ADDONE {
+    if random_chance < chance_addone {
         // Add a random city to random index - but only if it is not visited
         let visited_cities: HashSet<usize> = offspring.city_indexes.iter().cloned().collect();
-        let unvisited_cities: Vec<usize> = (0..139).filter(|n| !visited_cities.contains(n)).collect();
+        let unvisited_cities: Vec<usize> = (0..cities.names.len())
+            .filter(|n| !visited_cities.contains(n) && !config.avoided.contains(n))
+            .filter(|&n| coverage::district_has_room(&offspring.city_indexes, n, cities, config))
+            .collect();
         let insertion_index = rng.gen_range(0..offspring.city_indexes.len());
-        let mutation_value = unvisited_cities.choose(rng).unwrap();
-        offspring.city_indexes.insert(insertion_index, *mutation_value);
+        applied[2] = Some(false);
+        if let Some(mutation_value) = unvisited_cities.choose(rng) {
+            make(Move::Insert { position: insertion_index, city: *mutation_value }, &mut offspring.city_indexes);
+            applied[2] = Some(true);
+        }
     }
 
     random_chance = rng.gen_range(0..100);
-    if random_chance < MUTATION_CHANCE_REMOVEONE {
-        // Remove a random city from random index
-        let index = rng.gen_range(0..offspring.city_indexes.len());
-        offspring.city_indexes.remove(index);
+    if random_chance < chance_removeone {
+        // Remove a random city from random index, a required city only when it is visited more than once
+        let removable: Vec<usize> = (0..offspring.city_indexes.len()).filter(|&index| {
+            let city = offspring.city_indexes[index];
+            (!config.required.contains(&city) || offspring.city_indexes.iter().filter(|&&other| other == city).count() > 1)
+                && coverage::region_keeps_minimum(&offspring.city_indexes, index, cities, config)
+        }).collect();
+        applied[3] = Some(!removable.is_empty());
+        if !removable.is_empty() {
+            let index = removable[rng.gen_range(0..removable.len())];
+            make(Move::Remove { position: index }, &mut offspring.city_indexes);
+        }
     }
 
     random_chance = rng.gen_range(0..100);
-    if random_chance < MUTATION_CHANCE_REORDERONE {
-        // Take a random city and put it somewhere else
+    if random_chance < chance_reorderone {
+        applied[4] = Some(offspring.city_indexes.len() >= 2);
+    }
+    if random_chance < chance_reorderone && offspring.city_indexes.len() >= 2 {
+        // Take a random city and put it somewhere else (needs two places to pick from)
         let pick_from_index = rng.gen_range(0..offspring.city_indexes.len()); // First random index
         let mut insert_into_index = rng.gen_range(0..offspring.city_indexes.len()); // Second random index
         while pick_from_index == insert_into_index {
             insert_into_index = rng.gen_range(0..offspring.city_indexes.len());
         }
-        let picked_number = offspring.city_indexes.remove(pick_from_index); // Remove the picked number
-        offspring.city_indexes.insert(insert_into_index, picked_number);
+        make(Move::Relocate { from: pick_from_index, to: insert_into_index }, &mut offspring.city_indexes);
     }
 
-    offspring
+    if let Some(evaluation) = evaluation {
+        evaluation.fill(&mut offspring);
+    }
+    (offspring, applied)
 }
 
-fn crossover(parent1: &Path, parent2: &Path, rng: &mut ThreadRng) -> Path {
+pub fn crossover(parent1: &Path, parent2: &Path, kind: Crossover, rng: &mut StdRng) -> Path {
     // Determine the size of the offspring (let's just take the maximum size of the two parents)
     let offspring_size = rng.gen_range(1..=parent1.city_indexes.len().max(parent2.city_indexes.len()));
     let mut offspring = Vec::with_capacity(offspring_size);
 
-    let random_choice = rng.gen_range(0..2);
+    let random_choice = match kind {
+        Crossover::Mixed => rng.gen_range(0..2),
+        Crossover::OnePoint => 0,
+        Crossover::Slices => 1,
+    };
     match random_choice {
         0 => {
             // Choose a random crossover point
@@ -257,68 +577,151 @@ fn crossover(parent1: &Path, parent2: &Path, rng: &mut ThreadRng) -> Path {
     }
 }
 
-pub fn do_x_iterations(generation: &mut Generation, cities: &Cities, distances: &Distances, iterations: usize) -> Path {
-    let mut rng = rand::thread_rng();
+pub fn do_x_iterations(generation: &mut Generation, cities: &Cities, distances: &Distances, config: &Config, iterations: usize, rng: &mut StdRng) -> Path {
 
-    calculate_scores(generation, &cities, &distances);
+    generation.cache.use_config(config);
+    calculate_scores(generation, cities, distances, config);
+    // mutated paths are scored by their moves when the score is a plain sum over stops and legs,
+    // the score reaches calculate_scores through the cache, so without it they are scored there like the rest
+    let scoring = moves::Scoring::new(cities, distances, config);
+    let mutation_scoring = scoring.as_ref().filter(|_| config.score_cache);
 
-    let boundary_elite = GENERATION_SIZE * BOUNDARY_ELITE / 100;
-    let boundary_mutate = GENERATION_SIZE * BOUNDARY_MUTATE / 100;
-    let boundary_random = GENERATION_SIZE * BOUNDARY_RANDOM / 100;
-    for iteration in 1..=iterations {
+    let size = generation.paths.len();
+    let boundary_elite = size * config.ga.boundary_elite / 100;
+    let boundary_mutate = size * config.ga.boundary_mutate / 100;
+    let boundary_random = size * config.ga.boundary_random / 100;
+    for _iteration in 1..=iterations {
         // order paths by score
         // top 20 paths remain unchanged
         // next 20 paths mutate slightly (reorder single cities, or replace segments)
         // create 10 random paths
         // create 50 crossovers (pick random pairs from the 50)
 
-        for i in boundary_elite..boundary_mutate {
-            generation[i] = mutate(&generation[i], &mut rng);
+        // copies of a better route in the elite are mutated instead of kept, a tour and its reverse count as copies
+        if config.diversity {
+            for index in diversity::copies(&generation.paths[..boundary_elite]) {
+                generation.paths[index] = mutate(&generation.paths[index], cities, config, rng);
+            }
+        }
+        match &mut generation.rates {
+            None => {
+                let chances = config.ga.chances();
+                for path in generation.paths.iter_mut().take(boundary_mutate).skip(boundary_elite) {
+                    match mutation_scoring {
+                        Some(scoring) => {
+                            let mut evaluation = Evaluation::new(scoring, &path.city_indexes);
+                            *path = mutate_with_chances(path, cities, config, chances, Some(&mut evaluation), rng).0;
+                            generation.cache.store(path);
+                        }
+                        None => *path = mutate(path, cities, config, rng),
+                    }
+                }
+            }
+            // score the mutated paths right away, an operator is credited when it changed the path and beat the parent
+            Some(rates) => {
+                let chances = rates.chances();
+                for path in generation.paths.iter_mut().take(boundary_mutate).skip(boundary_elite) {
+                    let mut evaluation = mutation_scoring.map(|scoring| Evaluation::new(scoring, &path.city_indexes));
+                    let (mut offspring, applied) = mutate_with_chances(path, cities, config, chances, evaluation.as_mut(), rng);
+                    if evaluation.is_some() {
+                        generation.cache.store(&offspring);
+                    } else {
+                        calculate_score(&mut offspring, cities, distances, config);
+                    }
+                    rates.record(applied, offspring.score > path.score);
+                    *path = offspring;
+                }
+                rates.next_iteration();
+                if rates.iterations % adaptive::LOG_INTERVAL == 0 {
+                    println!("Mutation rates after {} iterations: {}", rates.iterations, rates);
+                }
+            }
         }
-        for i in boundary_mutate..boundary_random {
-            generation[i] = generate_random_path_with_rng(&mut rng);
+        for path in generation.paths.iter_mut().take(boundary_random).skip(boundary_mutate) {
+            *path = generate_path(cities, distances, config, rng);
         }
 
-        // single-threaded solution
-        for i in boundary_random..GENERATION_SIZE {
-            let parent1_index = rng.gen_range(0..boundary_random);
-            let parent2_index = rng.gen_range(0..boundary_random);
-            let parent1 = &generation[parent1_index];
-            let parent2 = &generation[parent2_index];
-            generation[i] = crossover(&parent1, &parent2, &mut rng);
+        // deterministic crowding: the route in a crossover slot is crossed both ways with a parent selected from the better part,
+        // the children are paired with the parents by edge distance and a child replaces its parent when it is better,
+        // so a route is only taken over by one that is close to it
+        if config.diversity {
+            for i in boundary_random..size {
+                let indexes = [i, rng.gen_range(0..boundary_random)];
+                let parents = [&generation.paths[indexes[0]], &generation.paths[indexes[1]]];
+                let mut children = [
+                    crossover(parents[0], parents[1], config.ga.crossover, rng),
+                    crossover(parents[1], parents[0], config.ga.crossover, rng),
+                ];
+                for child in children.iter_mut() {
+                    repair(child, cities, distances, config);
+                    if !generation.cache.lookup(child) {
+                        calculate_score(child, cities, distances, config);
+                        generation.cache.store(child);
+                    }
+                }
+                if diversity::children_cross(&children, parents) {
+                    children.swap(0, 1);
+                }
+                for (index, child) in indexes.into_iter().zip(children) {
+                    if child.score > generation.paths[index].score {
+                        generation.paths[index] = child;
+                    }
+                }
+            }
+        } else {
+            // single-threaded solution
+            for i in boundary_random..size {
+                let parent1_index = rng.gen_range(0..boundary_random);
+                let parent2_index = rng.gen_range(0..boundary_random);
+                let parent1 = &generation.paths[parent1_index];
+                let parent2 = &generation.paths[parent2_index];
+                let mut child = crossover(parent1, parent2, config.ga.crossover, rng);
+                repair(&mut child, cities, distances, config);
+                generation.paths[i] = child;
+            }
         }
 
         // evaluate this generation
-        calculate_scores(generation, &cities, &distances);
+        calculate_scores(generation, cities, distances, config);
     }
 
-    generation[0].clone()
+    if config.local_search {
+        if let Some(scoring) = &scoring {
+            moves::local_search(&mut generation.paths[0], scoring);
+        }
+    }
+    generation.paths[0].clone()
 }
 
-pub fn battle_royale() {
-    let cities = load_cities("cities.csv").unwrap();
-    let distances = load_distances("city_distance_matrix.csv").unwrap();
-    let mut rng = rand::thread_rng();
+// the seed and the config are printed first, so that a logged run can be archived and repeated
+pub fn battle_royale(cities: &Cities, distances: &Distances, config: &Config, seed: u64) -> Path {
+    let mut rng = StdRng::seed_from_u64(seed);
 
+    println!("Seed: {}", seed);
+    println!("Config: {}", config);
     println!("Starting battle..");
-    let mut golden_generation: Generation = std::array::from_fn(|_| {
-        generate_random_path_with_rng(&mut rng)
-    });
-
     let runs = 50;
+    // every run leaves its best path in the golden generation, so it has room for all of them even with a small generation_size
+    let golden_paths = (0..config.ga.generation_size.max(runs)).map(|_| {
+        generate_random_path_with_rng(&mut rng, cities.names.len(), config)
+    }).collect();
+    let mut golden_generation = Generation::new(golden_paths, config);
+
     for run in 0..runs {
-        let mut generation: Generation = std::array::from_fn(|_| {
-            generate_random_path_with_rng(&mut rng)
-        });
-        for tens_thousand in 0..ITERATION_COUNT/10000 {
-            let _best = do_x_iterations(&mut generation, &cities, &distances, 10000);
+        let mut generation = initial_generation(cities, distances, config, &mut rng);
+        for _tens_thousand in 0..ITERATION_COUNT/10000 {
+            let _best = do_x_iterations(&mut generation, cities, distances, config, 10000, &mut rng);
             println!("{}", _best);
+            println!("Diversity: {}", diversity::stats(&generation.paths));
+            println!("Score cache: {}", generation.cache);
         }
-        golden_generation[run] = generation[0].clone();
-        println!("CANDIDATE {}: {}", run, golden_generation[run]);
+        golden_generation.paths[run] = generation.paths[0].clone();
+        println!("CANDIDATE {}: {}", run, golden_generation.paths[run]);
     }
 
     println!("Battle royale begins");
-    let best = do_x_iterations(&mut golden_generation, &cities, &distances, 10000);
+    println!("Diversity before the battle royale: {}", diversity::stats(&golden_generation.paths));
+    let best = do_x_iterations(&mut golden_generation, cities, distances, config, 10000, &mut rng);
     println!("Best of the best: {}", best);
+    best
 }
@@ -0,0 +1,198 @@
+use std::fmt;
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::calculations::{Cities, Distances};
+use crate::timing::TimeModel;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Initializer {
+    Random,
+    NearestNeighbour,
+    Greedy,
+    Sweep,
+}
+
+// share of each initializer among the newly created paths, in percent
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitializerMix {
+    pub random: u32,
+    pub nearest: u32,
+    pub greedy: u32,
+    pub sweep: u32,
+}
+
+impl Default for InitializerMix {
+    fn default() -> Self {
+        InitializerMix { random: 100, nearest: 0, greedy: 0, sweep: 0 }
+    }
+}
+
+impl fmt::Display for InitializerMix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "random={},nearest={},greedy={},sweep={}", self.random, self.nearest, self.greedy, self.sweep)
+    }
+}
+
+impl InitializerMix {
+    // "random=40,nearest=20,greedy=20,sweep=20", left out initializers get 0
+    pub fn parse(text: &str) -> Result<InitializerMix, String> {
+        let mut mix = InitializerMix { random: 0, nearest: 0, greedy: 0, sweep: 0 };
+        for part in text.split(',') {
+            let (name, value) = part.split_once('=').ok_or(format!("expected name=percent, got '{}'", part))?;
+            let value: u32 = value.trim().parse().map_err(|_| format!("'{}' is not a percentage", value))?;
+            match name.trim() {
+                "random" => mix.random = value,
+                "nearest" => mix.nearest = value,
+                "greedy" => mix.greedy = value,
+                "sweep" => mix.sweep = value,
+                other => return Err(format!("unknown initializer '{}', use random, nearest, greedy or sweep", other)),
+            }
+        }
+        if mix.random + mix.nearest + mix.greedy + mix.sweep != 100 {
+            return Err(format!("percentages of '{}' do not add up to 100", text));
+        }
+        Ok(mix)
+    }
+
+    pub fn pick(&self, rng: &mut StdRng) -> Initializer {
+        let roll = rng.gen_range(0..100);
+        if roll < self.random {
+            Initializer::Random
+        } else if roll < self.random + self.nearest {
+            Initializer::NearestNeighbour
+        } else if roll < self.random + self.nearest + self.greedy {
+            Initializer::Greedy
+        } else {
+            Initializer::Sweep
+        }
+    }
+}
+
+// what a constructed tour has to respect, a time model without a budget is not checked
+#[derive(Clone, Copy)]
+pub struct Rules<'a> {
+    pub start: usize,
+    pub end: Option<usize>, // None ends wherever the budget runs out
+    pub km: Option<u32>,
+    pub time: Option<&'a TimeModel>,
+    pub avoid: &'a [usize], // cities never added
+    pub bad: &'a [usize],   // penalized cities, not added either
+}
+
+impl Rules<'_> {
+    // whether the tour, driven km and minutes so far at current, can go on to city and still reach the end in time
+    fn allows(&self, distances: &Distances, current: usize, city: usize, driven: u32, minutes: u32) -> bool {
+        let to_end = self.end.map_or(0, |end| distances[city][end]);
+        let km_ok = self.km.is_none_or(|km| driven + distances[current][city] + to_end <= km);
+        let time_ok = self.time.is_none_or(|time| time.budget.is_none_or(|budget| {
+            let to_end = self.end.map_or(0, |end| time.leg(city, end, distances));
+            minutes + time.leg(current, city, distances) + time.dwell + to_end <= budget
+        }));
+        km_ok && time_ok
+    }
+
+    fn minutes(&self, distances: &Distances, from: usize, to: usize) -> u32 {
+        self.time.map_or(0, |time| time.leg(from, to, distances) + time.dwell)
+    }
+
+    // cities that may be added to a tour
+    fn candidates(&self, cities: &Cities) -> Vec<usize> {
+        (0..cities.names.len())
+            .filter(|&city| city != self.start && Some(city) != self.end && !self.bad.contains(&city) && !self.avoid.contains(&city))
+            .collect()
+    }
+
+    fn finish(&self, mut tour: Vec<usize>) -> Vec<usize> {
+        if let Some(end) = self.end {
+            tour.push(end);
+        }
+        tour
+    }
+}
+
+// extends the tour (which begins at the start) with cities chosen by choose, as long as the way to the end stays within the rules
+fn build_tour(distances: &Distances, rules: Rules, mut remaining: Vec<usize>, mut choose: impl FnMut(usize, &[usize]) -> Option<usize>) -> Vec<usize> {
+    let mut tour = vec![rules.start];
+    let mut driven = 0;
+    let mut minutes = 0;
+    loop {
+        let current = *tour.last().unwrap();
+        let feasible: Vec<usize> = remaining.iter().copied()
+            .filter(|&city| rules.allows(distances, current, city, driven, minutes))
+            .collect();
+        let Some(next) = choose(current, &feasible) else {
+            break;
+        };
+        driven += distances[current][next];
+        minutes += rules.minutes(distances, current, next);
+        tour.push(next);
+        remaining.retain(|&city| city != next);
+    }
+    rules.finish(tour)
+}
+
+// random first city, then always the closest one
+pub fn nearest_neighbour(cities: &Cities, distances: &Distances, rules: Rules, rng: &mut StdRng) -> Vec<usize> {
+    build_tour(distances, rules, rules.candidates(cities), |current, feasible| {
+        if feasible.is_empty() {
+            None
+        } else if current == rules.start {
+            Some(feasible[rng.gen_range(0..feasible.len())])
+        } else {
+            feasible.iter().copied().min_by_key(|&city| distances[current][city])
+        }
+    })
+}
+
+// next city drawn with probability proportional to population per kilometre
+pub fn randomized_greedy(cities: &Cities, distances: &Distances, rules: Rules, rng: &mut StdRng) -> Vec<usize> {
+    build_tour(distances, rules, rules.candidates(cities), |current, feasible| {
+        let weights: Vec<f64> = feasible.iter()
+            .map(|&city| f64::from(cities.populations[city]) / f64::from(distances[current][city] + 1))
+            .collect();
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut roll = rng.gen_range(0.0..total);
+        for (city, weight) in feasible.iter().zip(&weights) {
+            if roll < *weight {
+                return Some(*city);
+            }
+            roll -= weight;
+        }
+        feasible.last().copied()
+    })
+}
+
+// cities ordered by their angle around the start, beginning at a random angle and going in a random direction
+// a city is skipped when it does not fit into the rest of the budget
+pub fn sweep(cities: &Cities, distances: &Distances, rules: Rules, rng: &mut StdRng) -> Vec<usize> {
+    let (start_x, start_y) = cities.coords[rules.start];
+    let start_angle = rng.gen_range(-std::f32::consts::PI..std::f32::consts::PI);
+    let clockwise = rng.gen_bool(0.5);
+    let angle = |city: usize| {
+        let (x, y) = cities.coords[city];
+        let mut angle = (y - start_y).atan2(x - start_x) - start_angle;
+        if clockwise {
+            angle = -angle;
+        }
+        angle.rem_euclid(2.0 * std::f32::consts::PI)
+    };
+    let mut ordered = rules.candidates(cities);
+    ordered.sort_by(|&a, &b| angle(a).total_cmp(&angle(b)));
+
+    let mut tour = vec![rules.start];
+    let mut driven = 0;
+    let mut minutes = 0;
+    for city in ordered {
+        let current = *tour.last().unwrap();
+        if rules.allows(distances, current, city, driven, minutes) {
+            driven += distances[current][city];
+            minutes += rules.minutes(distances, current, city);
+            tour.push(city);
+        }
+    }
+    rules.finish(tour)
+}
@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use crate::calculations::{self, Cities, Config, Distances, Grouping, Path};
+
+// visited cities in every group of the grouping
+pub fn group_counts(grouping: &Grouping, visited: &HashSet<usize>) -> Vec<u32> {
+    let mut counts = vec![0; grouping.names.len()];
+    for &city in visited {
+        if let Some(group) = grouping.of_city.get(city).copied().flatten() {
+            counts[group] += 1;
+        }
+    }
+    counts
+}
+
+// number of groups with at least one visited city
+pub fn covered(grouping: &Grouping, visited: &HashSet<usize>) -> usize {
+    group_counts(grouping, visited).iter().filter(|&&count| count > 0).count()
+}
+
+// cities visited too few times per region plus cities visited too many times per district
+pub fn violations(visited: &HashSet<usize>, cities: &Cities, config: &Config) -> u32 {
+    let mut violations = 0;
+    if config.min_per_region > 0 {
+        violations += group_counts(&cities.regions, visited).iter().map(|count| config.min_per_region.saturating_sub(*count)).sum::<u32>();
+    }
+    if let Some(max) = config.max_per_district {
+        violations += group_counts(&cities.districts, visited).iter().map(|count| count.saturating_sub(max)).sum::<u32>();
+    }
+    violations
+}
+
+fn group_of(grouping: &Grouping, city: usize) -> Option<usize> {
+    grouping.of_city.get(city).copied().flatten()
+}
+
+// whether one more city in the district of city stays within max_per_district
+pub fn district_has_room(stops: &[usize], city: usize, cities: &Cities, config: &Config) -> bool {
+    let (Some(max), Some(district)) = (config.max_per_district, group_of(&cities.districts, city)) else {
+        return true;
+    };
+    let in_district: HashSet<usize> = stops.iter().copied().filter(|&other| group_of(&cities.districts, other) == Some(district)).collect();
+    in_district.contains(&city) || (in_district.len() as u32) < max
+}
+
+// whether the stop at position can be dropped without its region falling below min_per_region
+pub fn region_keeps_minimum(stops: &[usize], position: usize, cities: &Cities, config: &Config) -> bool {
+    let city = stops[position];
+    let Some(region) = group_of(&cities.regions, city) else {
+        return true;
+    };
+    if config.min_per_region == 0 || stops.iter().filter(|&&other| other == city).count() > 1 {
+        return true;
+    }
+    let in_region: HashSet<usize> = stops.iter().copied().filter(|&other| group_of(&cities.regions, other) == Some(region)).collect();
+    in_region.len() as u32 > config.min_per_region
+}
+
+// adds the cheapest cities of every region below the minimum and drops the smallest cities of every district over the maximum
+// required cities, the start and the end are never dropped
+pub fn repair(path: &mut Path, cities: &Cities, distances: &Distances, config: &Config) {
+    if config.min_per_region > 0 {
+        for region in 0..cities.regions.names.len() {
+            loop {
+                let stops = &path.city_indexes;
+                let in_region: HashSet<usize> = stops.iter().copied().filter(|&city| group_of(&cities.regions, city) == Some(region)).collect();
+                if in_region.len() as u32 >= config.min_per_region {
+                    break;
+                }
+                let best = (0..cities.names.len())
+                    .filter(|&city| group_of(&cities.regions, city) == Some(region) && !in_region.contains(&city))
+                    .filter(|&city| !config.avoided.contains(&city) && !config.is_bad_city(city))
+                    .filter(|&city| district_has_room(stops, city, cities, config))
+                    .map(|city| (city, calculations::cheapest_insertion(stops, city, distances, config)))
+                    .min_by_key(|(_, (_, cost))| *cost);
+                let Some((city, (position, _))) = best else {
+                    break;
+                };
+                path.city_indexes.insert(position, city);
+            }
+        }
+    }
+
+    if let Some(max) = config.max_per_district {
+        for district in 0..cities.districts.names.len() {
+            loop {
+                let stops = &path.city_indexes;
+                let inner = 1..stops.len().saturating_sub(1);
+                let in_district: HashSet<usize> = stops.iter().copied().filter(|&city| group_of(&cities.districts, city) == Some(district)).collect();
+                if in_district.len() as u32 <= max {
+                    break;
+                }
+                let smallest = inner.clone()
+                    .filter(|&position| group_of(&cities.districts, stops[position]) == Some(district))
+                    .filter(|&position| {
+                        let city = stops[position];
+                        city != config.start && Some(city) != config.end && !config.required.contains(&city)
+                    })
+                    .filter(|&position| region_keeps_minimum(stops, position, cities, config))
+                    .min_by_key(|&position| cities.populations[stops[position]]);
+                let Some(position) = smallest else {
+                    break;
+                };
+                let city = stops[position];
+                let keep_last = stops.len() - 1;
+                let mut index = 0;
+                path.city_indexes.retain(|&other| {
+                    let keep = other != city || index == 0 || index == keep_last;
+                    index += 1;
+                    keep
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,516 @@
+use std::error::Error;
+use std::fmt;
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::calculations::{Cities, Distances, Grouping};
+use crate::geo;
+use crate::timing;
+
+pub const CITIES_FILE: &str = "cities.csv";
+pub const DISTANCES_FILE: &str = "city_distance_matrix.csv";
+
+// accepted header names for the columns of cities.csv (compared case-insensitively)
+const NAME_COLUMNS: [&str; 3] = ["city name", "name", "city"];
+const POPULATION_COLUMNS: [&str; 2] = ["population", "pop"];
+const LONGITUDE_COLUMNS: [&str; 3] = ["longitude", "lon", "lng"];
+const LATITUDE_COLUMNS: [&str; 2] = ["latitude", "lat"];
+// optional, without it every city can be an overnight stop
+const OVERNIGHT_COLUMNS: [&str; 3] = ["overnight", "overnight stop", "hotel"];
+// optional as well, a custom value of every city and the region and district it belongs to
+const VALUE_COLUMNS: [&str; 2] = ["value", "custom value"];
+const REGION_COLUMNS: [&str; 2] = ["region", "kraj"];
+const DISTRICT_COLUMNS: [&str; 2] = ["district", "okres"];
+// columns of the opening hours file
+const OPEN_COLUMNS: [&str; 3] = ["open", "opens", "from"];
+const CLOSE_COLUMNS: [&str; 3] = ["close", "closes", "until"];
+
+// single problem found in a data file
+// row and column are 1-based and point into the file (the header is row 1)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataError {
+    pub file: String,
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+    pub reason: String,
+}
+
+impl DataError {
+    pub fn new(file: &str, row: Option<usize>, column: Option<usize>, reason: String) -> DataError {
+        DataError { file: file.to_string(), row, column, reason }
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(row) = self.row {
+            write!(f, ", row {}", row)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ", column {}", column)?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+impl Error for DataError {}
+
+// raw content of the distance matrix file, kept around so that it can be checked against cities.csv
+struct Matrix {
+    header: Vec<String>,
+    labels: Vec<String>,
+    lines: Vec<usize>,
+    rows: Distances,
+}
+
+fn find_column(headers: &StringRecord, accepted: &[&str]) -> Option<usize> {
+    headers.iter().position(|header| {
+        let header = header.trim().to_lowercase();
+        accepted.iter().any(|name| *name == header)
+    })
+}
+
+fn record_line(record: &StringRecord, fallback: usize) -> usize {
+    record.position().map_or(fallback, |position| position.line() as usize)
+}
+
+fn parse_cell<T: std::str::FromStr>(filename: &str, record: &StringRecord, line: usize, column: usize, what: &str, errors: &mut Vec<DataError>) -> Option<T> {
+    let Some(value) = record.get(column) else {
+        errors.push(DataError::new(filename, Some(line), Some(column + 1), format!("missing {}", what)));
+        return None;
+    };
+    match value.trim().parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            errors.push(DataError::new(filename, Some(line), Some(column + 1), format!("{} '{}' is not a valid number", what, value)));
+            None
+        }
+    }
+}
+
+// yes/no cell, an empty one is no
+fn parse_flag(filename: &str, record: &StringRecord, line: usize, column: usize, what: &str, errors: &mut Vec<DataError>) -> Option<bool> {
+    let value = record.get(column).unwrap_or("").trim();
+    match value.to_lowercase().as_str() {
+        "1" | "yes" | "y" | "true" | "x" => Some(true),
+        "0" | "no" | "n" | "false" | "" => Some(false),
+        _ => {
+            errors.push(DataError::new(filename, Some(line), Some(column + 1), format!("{} '{}' is not yes or no", what, value)));
+            None
+        }
+    }
+}
+
+// the next city belongs to the named group, an empty name means no group
+fn add_to_grouping(grouping: &mut Grouping, name: Option<&str>) {
+    let name = name.unwrap_or("").trim();
+    let group = if name.is_empty() {
+        None
+    } else if let Some(group) = grouping.names.iter().position(|known| known == name) {
+        Some(group)
+    } else {
+        grouping.names.push(name.to_string());
+        Some(grouping.names.len() - 1)
+    };
+    grouping.of_city.push(group);
+}
+
+// reads cities.csv, every problem is pushed into errors and the offending row is skipped
+fn read_cities(filename: &str, errors: &mut Vec<DataError>) -> Cities {
+    let mut cities = Cities {
+        names: Vec::new(),
+        populations: Vec::new(),
+        coords: Vec::new(),
+        overnight: Vec::new(),
+        values: Vec::new(),
+        regions: Grouping::default(),
+        districts: Grouping::default(),
+    };
+    let mut rdr = match ReaderBuilder::new().flexible(true).from_path(filename) {
+        Ok(rdr) => rdr,
+        Err(error) => {
+            errors.push(DataError::new(filename, None, None, error.to_string()));
+            return cities;
+        }
+    };
+    let headers = match rdr.headers() {
+        Ok(headers) => headers.clone(),
+        Err(error) => {
+            errors.push(DataError::new(filename, Some(1), None, error.to_string()));
+            return cities;
+        }
+    };
+
+    // locate the columns by their header instead of relying on their position
+    let mut columns = Vec::new();
+    for (what, accepted) in [("name", &NAME_COLUMNS[..]), ("population", &POPULATION_COLUMNS[..]), ("longitude", &LONGITUDE_COLUMNS[..]), ("latitude", &LATITUDE_COLUMNS[..])] {
+        match find_column(&headers, accepted) {
+            Some(column) => columns.push(column),
+            None => errors.push(DataError::new(filename, Some(1), None, format!("no {} column, expected one of {:?}", what, accepted))),
+        }
+    }
+    if columns.len() < 4 {
+        return cities;
+    }
+    let (name_column, population_column, longitude_column, latitude_column) = (columns[0], columns[1], columns[2], columns[3]);
+    let overnight_column = find_column(&headers, &OVERNIGHT_COLUMNS);
+    let value_column = find_column(&headers, &VALUE_COLUMNS);
+    let region_column = find_column(&headers, &REGION_COLUMNS);
+    let district_column = find_column(&headers, &DISTRICT_COLUMNS);
+
+    for (index, result) in rdr.records().enumerate() {
+        let record = match result {
+            Ok(record) => record,
+            Err(error) => {
+                errors.push(DataError::new(filename, Some(index + 2), None, error.to_string()));
+                continue;
+            }
+        };
+        let line = record_line(&record, index + 2);
+
+        let name = record.get(name_column).map(|name| name.trim().to_string()).unwrap_or_default();
+        if name.is_empty() {
+            errors.push(DataError::new(filename, Some(line), Some(name_column + 1), "missing city name".to_string()));
+        }
+        let population: Option<u32> = parse_cell(filename, &record, line, population_column, "population", errors);
+        let longitude: Option<f32> = parse_cell(filename, &record, line, longitude_column, "longitude", errors);
+        let latitude: Option<f32> = parse_cell(filename, &record, line, latitude_column, "latitude", errors);
+        if let Some(longitude) = longitude {
+            if !(-180.0..=180.0).contains(&longitude) {
+                errors.push(DataError::new(filename, Some(line), Some(longitude_column + 1), format!("longitude {} is out of range", longitude)));
+            }
+        }
+        if let Some(latitude) = latitude {
+            if !(-90.0..=90.0).contains(&latitude) {
+                errors.push(DataError::new(filename, Some(line), Some(latitude_column + 1), format!("latitude {} is out of range", latitude)));
+            }
+        }
+
+        let overnight = match overnight_column {
+            Some(column) => parse_flag(filename, &record, line, column, "overnight flag", errors),
+            None => Some(true),
+        };
+        // an empty value cell is a value of 0, without the column there are no values at all
+        let value = match value_column {
+            Some(column) if record.get(column).is_some_and(|value| !value.trim().is_empty()) => {
+                parse_cell(filename, &record, line, column, "value", errors).map(Some)
+            }
+            Some(_) => Some(Some(0)),
+            None => Some(None),
+        };
+
+        if let (false, Some(population), Some(longitude), Some(latitude), Some(overnight), Some(value)) = (name.is_empty(), population, longitude, latitude, overnight, value) {
+            cities.names.push(name);
+            cities.populations.push(population);
+            cities.coords.push((longitude, latitude));
+            cities.overnight.push(overnight);
+            cities.values.push(value);
+            add_to_grouping(&mut cities.regions, region_column.and_then(|column| record.get(column)));
+            add_to_grouping(&mut cities.districts, district_column.and_then(|column| record.get(column)));
+        }
+    }
+
+    if cities.names.is_empty() && errors.is_empty() {
+        errors.push(DataError::new(filename, None, None, "file contains no cities".to_string()));
+    }
+    cities
+}
+
+// reads the distance matrix, every problem is pushed into errors
+// only checks that need nothing but the matrix itself are done here
+fn read_matrix(filename: &str, errors: &mut Vec<DataError>) -> Matrix {
+    let mut matrix = Matrix { header: Vec::new(), labels: Vec::new(), lines: Vec::new(), rows: Vec::new() };
+    let mut rdr = match ReaderBuilder::new().flexible(true).from_path(filename) {
+        Ok(rdr) => rdr,
+        Err(error) => {
+            errors.push(DataError::new(filename, None, None, error.to_string()));
+            return matrix;
+        }
+    };
+    match rdr.headers() {
+        Ok(headers) => matrix.header = headers.iter().skip(1).map(|name| name.trim().to_string()).collect(),
+        Err(error) => {
+            errors.push(DataError::new(filename, Some(1), None, error.to_string()));
+            return matrix;
+        }
+    }
+
+    for (index, result) in rdr.records().enumerate() {
+        let record = match result {
+            Ok(record) => record,
+            Err(error) => {
+                errors.push(DataError::new(filename, Some(index + 2), None, error.to_string()));
+                continue;
+            }
+        };
+        let line = record_line(&record, index + 2);
+        let mut row = Vec::with_capacity(record.len().saturating_sub(1));
+        for column in 1..record.len() {
+            // keep the row aligned even if a cell is broken, the error is reported anyway
+            let value: Option<u32> = parse_cell(filename, &record, line, column, "distance", errors);
+            row.push(value.unwrap_or(0));
+        }
+        matrix.labels.push(record.get(0).unwrap_or_default().trim().to_string());
+        matrix.lines.push(line);
+        matrix.rows.push(row);
+    }
+
+    let size = matrix.rows.len();
+    if size == 0 {
+        errors.push(DataError::new(filename, None, None, "matrix has no rows".to_string()));
+    }
+    if matrix.header.len() != size {
+        errors.push(DataError::new(filename, Some(1), None, format!("header lists {} cities but the matrix has {} rows", matrix.header.len(), size)));
+    }
+    for (row_index, row) in matrix.rows.iter().enumerate() {
+        if row.len() != size {
+            errors.push(DataError::new(filename, Some(matrix.lines[row_index]), None, format!("row has {} distances, the matrix is not square ({} rows)", row.len(), size)));
+        }
+        if let Some(&diagonal) = row.get(row_index) {
+            if diagonal != 0 {
+                errors.push(DataError::new(filename, Some(matrix.lines[row_index]), Some(row_index + 2), format!("distance from '{}' to itself is {}, expected 0", matrix.labels[row_index], diagonal)));
+            }
+        }
+    }
+    matrix
+}
+
+// checks that the matrix describes exactly the cities from cities.csv, in the same order
+fn check_matrix_against_cities(filename: &str, matrix: &Matrix, cities: &Cities, errors: &mut Vec<DataError>) {
+    if matrix.rows.is_empty() {
+        return; // nothing was read, the reason has already been reported
+    }
+    if matrix.rows.len() != cities.names.len() {
+        errors.push(DataError::new(filename, None, None, format!("matrix has {} rows but there are {} cities", matrix.rows.len(), cities.names.len())));
+    }
+    for (index, name) in matrix.header.iter().enumerate() {
+        if let Some(expected) = cities.names.get(index) {
+            if name != expected {
+                errors.push(DataError::new(filename, Some(1), Some(index + 2), format!("header '{}' does not match city {} '{}'", name, index, expected)));
+            }
+        }
+    }
+    for (index, label) in matrix.labels.iter().enumerate() {
+        if let Some(expected) = cities.names.get(index) {
+            if label != expected {
+                errors.push(DataError::new(filename, Some(matrix.lines[index]), Some(1), format!("row label '{}' does not match city {} '{}'", label, index, expected)));
+            }
+        }
+    }
+}
+
+fn first_error<T>(value: T, errors: Vec<DataError>) -> Result<T, DataError> {
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(value),
+    }
+}
+
+pub fn load_cities(filename: &str) -> Result<Cities, DataError> {
+    let mut errors = Vec::new();
+    let cities = read_cities(filename, &mut errors);
+    first_error(cities, errors)
+}
+
+// the matrix is checked against the cities it is going to be used with
+pub fn load_distances(filename: &str, cities: &Cities) -> Result<Distances, DataError> {
+    let mut errors = Vec::new();
+    let matrix = read_matrix(filename, &mut errors);
+    check_matrix_against_cities(filename, &matrix, cities, &mut errors);
+    first_error(matrix.rows, errors)
+}
+
+// opening hours, one row per city that has them: name,open,close e.g. "Gelnica,8:00,16:00"
+// cities are matched by name, the ones not listed are always open
+fn read_windows(filename: &str, cities: &Cities, errors: &mut Vec<DataError>) -> Vec<Option<(u32, u32)>> {
+    let mut windows = vec![None; cities.names.len()];
+    let mut rdr = match ReaderBuilder::new().flexible(true).from_path(filename) {
+        Ok(rdr) => rdr,
+        Err(error) => {
+            errors.push(DataError::new(filename, None, None, error.to_string()));
+            return windows;
+        }
+    };
+    let headers = match rdr.headers() {
+        Ok(headers) => headers.clone(),
+        Err(error) => {
+            errors.push(DataError::new(filename, Some(1), None, error.to_string()));
+            return windows;
+        }
+    };
+    let mut columns = Vec::new();
+    for (what, accepted) in [("name", &NAME_COLUMNS[..]), ("open", &OPEN_COLUMNS[..]), ("close", &CLOSE_COLUMNS[..])] {
+        match find_column(&headers, accepted) {
+            Some(column) => columns.push(column),
+            None => errors.push(DataError::new(filename, Some(1), None, format!("no {} column, expected one of {:?}", what, accepted))),
+        }
+    }
+    if columns.len() < 3 {
+        return windows;
+    }
+
+    for (index, result) in rdr.records().enumerate() {
+        let record = match result {
+            Ok(record) => record,
+            Err(error) => {
+                errors.push(DataError::new(filename, Some(index + 2), None, error.to_string()));
+                continue;
+            }
+        };
+        let line = record_line(&record, index + 2);
+        let name = record.get(columns[0]).unwrap_or("").trim();
+        let Some(city) = cities.names.iter().position(|known| known == name) else {
+            errors.push(DataError::new(filename, Some(line), Some(columns[0] + 1), format!("unknown city '{}'", name)));
+            continue;
+        };
+        let mut times = Vec::new();
+        for column in [columns[1], columns[2]] {
+            let value = record.get(column).unwrap_or("");
+            match timing::parse_hours(value) {
+                Ok(minutes) => times.push(minutes),
+                Err(reason) => errors.push(DataError::new(filename, Some(line), Some(column + 1), reason)),
+            }
+        }
+        if let [open, close] = times[..] {
+            if open > close {
+                errors.push(DataError::new(filename, Some(line), None, format!("{} closes before it opens", name)));
+            } else {
+                windows[city] = Some((open, close));
+            }
+        }
+    }
+    windows
+}
+
+pub fn load_windows(filename: &str, cities: &Cities) -> Result<Vec<Option<(u32, u32)>>, DataError> {
+    let mut errors = Vec::new();
+    let windows = read_windows(filename, cities, &mut errors);
+    first_error(windows, errors)
+}
+
+// without a matrix file the distances are estimated from the city coordinates, great-circle km times the detour factor
+pub fn load_dataset(cities_file: &str, distances_file: &str, detour_factor: f64) -> Result<(Cities, Distances), DataError> {
+    let cities = load_cities(cities_file)?;
+    if !std::path::Path::new(distances_file).exists() {
+        println!("{} not found, estimating distances from coordinates (detour factor {})", distances_file, detour_factor);
+        let distances = geo::distances_from_coords(&cities, detour_factor);
+        return Ok((cities, distances));
+    }
+    let distances = load_distances(distances_file, &cities)?;
+    Ok((cities, distances))
+}
+
+// returns every problem in the dataset instead of stopping at the first one
+pub fn validate_dataset(cities_file: &str, distances_file: &str) -> Vec<DataError> {
+    let mut errors = Vec::new();
+    let cities = read_cities(cities_file, &mut errors);
+    let cities_valid = errors.is_empty();
+    let matrix = read_matrix(distances_file, &mut errors);
+    // broken rows in cities.csv would shift every name after them, so compare only against a clean city list
+    if cities_valid {
+        check_matrix_against_cities(distances_file, &matrix, &cities, &mut errors);
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // writes the content to a file in the temp directory, named after the test so that tests running in parallel do not clash
+    fn temp_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("gelnica-chopper-data-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    const CITIES: &str = "name,population,lon,lat\nGelnica,6000,20.9,48.8\nKošice,230000,21.3,48.7\nPrešov,85000,21.2,49.0\n";
+    const MATRIX: &str = ",Gelnica,Košice,Prešov\nGelnica,0,40,50\nKošice,40,0,36\nPrešov,50,36,0\n";
+
+    fn reasons(errors: &[DataError]) -> Vec<(Option<usize>, Option<usize>, &str)> {
+        errors.iter().map(|error| (error.row, error.column, error.reason.as_str())).collect()
+    }
+
+    #[test]
+    fn a_valid_dataset_loads() {
+        let cities = temp_file("valid-cities.csv", CITIES);
+        let matrix = temp_file("valid-matrix.csv", MATRIX);
+        assert!(validate_dataset(&cities, &matrix).is_empty());
+        let (cities, distances) = load_dataset(&cities, &matrix, 1.0).unwrap();
+        assert_eq!(cities.names, ["Gelnica", "Košice", "Prešov"]);
+        assert_eq!(cities.populations, [6000, 230000, 85000]);
+        assert_eq!(distances[1], [40, 0, 36]);
+    }
+
+    #[test]
+    fn a_matrix_that_is_not_square_is_rejected() {
+        let matrix = temp_file("square-matrix.csv", ",Gelnica,Košice,Prešov\nGelnica,0,40,50\nKošice,40,0\nPrešov,50,36,0\n");
+        let mut errors = Vec::new();
+        read_matrix(&matrix, &mut errors);
+        assert_eq!(reasons(&errors), [(Some(3), None, "row has 2 distances, the matrix is not square (3 rows)")]);
+    }
+
+    #[test]
+    fn a_matrix_of_another_size_than_the_cities_is_rejected() {
+        let cities = temp_file("size-cities.csv", "name,population,lon,lat\nGelnica,6000,20.9,48.8\nKošice,230000,21.3,48.7\n");
+        let matrix = temp_file("size-matrix.csv", MATRIX);
+        let errors = validate_dataset(&cities, &matrix);
+        assert_eq!(reasons(&errors), [(None, None, "matrix has 3 rows but there are 2 cities")]);
+        assert_eq!(load_dataset(&cities, &matrix, 1.0).err(), errors.first().cloned());
+    }
+
+    #[test]
+    fn a_non_zero_diagonal_is_rejected() {
+        let matrix = temp_file("diagonal-matrix.csv", ",Gelnica,Košice,Prešov\nGelnica,0,40,50\nKošice,40,3,36\nPrešov,50,36,0\n");
+        let mut errors = Vec::new();
+        read_matrix(&matrix, &mut errors);
+        assert_eq!(reasons(&errors), [(Some(3), Some(3), "distance from 'Košice' to itself is 3, expected 0")]);
+    }
+
+    #[test]
+    fn headers_and_labels_must_match_the_cities() {
+        let cities = temp_file("labels-cities.csv", CITIES);
+        let matrix = temp_file("labels-matrix.csv", ",Gelnica,Presov,Košice\nGelnica,0,40,50\nKosice,40,0,36\nPrešov,50,36,0\n");
+        let errors = validate_dataset(&cities, &matrix);
+        assert_eq!(reasons(&errors), [
+            (Some(1), Some(3), "header 'Presov' does not match city 1 'Košice'"),
+            (Some(1), Some(4), "header 'Košice' does not match city 2 'Prešov'"),
+            (Some(3), Some(1), "row label 'Kosice' does not match city 1 'Košice'"),
+        ]);
+    }
+
+    #[test]
+    fn unparsable_cells_are_reported_where_they_are() {
+        let cities = temp_file("cells-cities.csv", "name,population,lon,lat\nGelnica,many,20.9,48.8\nKošice,230000,east,48.7\n");
+        let mut errors = Vec::new();
+        let read = read_cities(&cities, &mut errors);
+        assert!(read.names.is_empty());
+        assert_eq!(reasons(&errors), [
+            (Some(2), Some(2), "population 'many' is not a valid number"),
+            (Some(3), Some(3), "longitude 'east' is not a valid number"),
+        ]);
+
+        let matrix = temp_file("cells-matrix.csv", ",Gelnica,Košice\nGelnica,0,-4\nKošice,4 0,0\n");
+        let mut errors = Vec::new();
+        read_matrix(&matrix, &mut errors);
+        assert_eq!(reasons(&errors), [
+            (Some(2), Some(3), "distance '-4' is not a valid number"),
+            (Some(3), Some(2), "distance '4 0' is not a valid number"),
+        ]);
+    }
+
+    #[test]
+    fn validation_reports_every_problem_at_once() {
+        let cities = temp_file("all-cities.csv", "name,population,lon,lat\nGelnica,6000,20.9,48.8\n,100,21.0,48.0\nKošice,230000,21.3,95\n");
+        let matrix = temp_file("all-matrix.csv", ",Gelnica,Košice\nGelnica,1,x\nKošice,40\n");
+        let errors = validate_dataset(&cities, &matrix);
+        assert_eq!(errors.iter().map(|error| error.file.as_str()).collect::<Vec<_>>(), [&cities, &cities, &matrix, &matrix, &matrix]);
+        assert_eq!(reasons(&errors), [
+            (Some(3), Some(1), "missing city name"),
+            (Some(4), Some(4), "latitude 95 is out of range"),
+            (Some(2), Some(3), "distance 'x' is not a valid number"),
+            (Some(2), Some(2), "distance from 'Gelnica' to itself is 1, expected 0"),
+            (Some(3), None, "row has 1 distances, the matrix is not square (2 rows)"),
+        ]);
+    }
+}
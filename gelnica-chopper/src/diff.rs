@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+
+use crate::calculations::Path;
+
+pub struct RouteDiff {
+    pub only_in_a: Vec<usize>,
+    pub only_in_b: Vec<usize>,
+    pub legs_only_in_a: Vec<(usize, usize)>,
+    pub legs_only_in_b: Vec<(usize, usize)>,
+    pub reordered: Vec<usize>, // cities of both routes that moved relative to the others
+    pub b_reversed: bool,      // B is compared in the opposite driving direction
+    pub distance_change: i64,
+    pub population_change: i64,
+    pub score_change: i64,
+}
+
+// legs are compared without direction, the road is the same both ways
+pub fn legs(path: &Path) -> HashSet<(usize, usize)> {
+    path.city_indexes.windows(2).map(|leg| (leg[0].min(leg[1]), leg[0].max(leg[1]))).collect()
+}
+
+// longest common subsequence of two orders, returned as the set of cities that are part of it
+fn longest_common_subsequence(a: &[usize], b: &[usize]) -> HashSet<usize> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] { lengths[i + 1][j + 1] + 1 } else { lengths[i + 1][j].max(lengths[i][j + 1]) };
+        }
+    }
+    let mut result = HashSet::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            result.insert(a[i]);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+// first visit of every city, in driving order
+fn visit_order<'a>(city_indexes: impl Iterator<Item = &'a usize>, keep: &HashSet<usize>) -> Vec<usize> {
+    let mut seen = HashSet::new();
+    city_indexes.copied().filter(|city| keep.contains(city) && seen.insert(*city)).collect()
+}
+
+// both paths are expected to be scored
+pub fn diff_routes(a: &Path, b: &Path) -> RouteDiff {
+    let cities_a: HashSet<usize> = a.city_indexes.iter().copied().collect();
+    let cities_b: HashSet<usize> = b.city_indexes.iter().copied().collect();
+    let mut only_in_a: Vec<usize> = cities_a.difference(&cities_b).copied().collect();
+    let mut only_in_b: Vec<usize> = cities_b.difference(&cities_a).copied().collect();
+    only_in_a.sort();
+    only_in_b.sort();
+
+    let (legs_a, legs_b) = (legs(a), legs(b));
+    let mut legs_only_in_a: Vec<(usize, usize)> = legs_a.difference(&legs_b).copied().collect();
+    let mut legs_only_in_b: Vec<(usize, usize)> = legs_b.difference(&legs_a).copied().collect();
+    legs_only_in_a.sort();
+    legs_only_in_b.sort();
+
+    // a loop driven the other way round is not a reorder, so compare against the better direction of B
+    let common: HashSet<usize> = cities_a.intersection(&cities_b).copied().collect();
+    let order_a = visit_order(a.city_indexes.iter(), &common);
+    let order_b = visit_order(b.city_indexes.iter(), &common);
+    let order_b_reversed = visit_order(b.city_indexes.iter().rev(), &common);
+    let kept_forward = longest_common_subsequence(&order_a, &order_b);
+    let kept_reversed = longest_common_subsequence(&order_a, &order_b_reversed);
+    let b_reversed = kept_reversed.len() > kept_forward.len();
+    let kept = if b_reversed { kept_reversed } else { kept_forward };
+    let reordered: Vec<usize> = order_a.iter().copied().filter(|city| !kept.contains(city)).collect();
+
+    RouteDiff {
+        only_in_a,
+        only_in_b,
+        legs_only_in_a,
+        legs_only_in_b,
+        reordered,
+        b_reversed,
+        distance_change: i64::from(b.distance) - i64::from(a.distance),
+        population_change: i64::from(b.population) - i64::from(a.population),
+        score_change: i64::from(b.score) - i64::from(a.score),
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::calculations::Path;
+
+// undirected edges of the route, sorted, a route and its reverse have the same ones
+pub fn edges(stops: &[usize]) -> Vec<(usize, usize)> {
+    let mut edges: Vec<(usize, usize)> = stops.windows(2).map(|leg| (leg[0].min(leg[1]), leg[0].max(leg[1]))).collect();
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+}
+
+// the route or its reverse, whichever is smaller, so that a tour and its reverse compare equal
+pub fn canonical(stops: &[usize]) -> Vec<usize> {
+    let reversed: Vec<usize> = stops.iter().rev().copied().collect();
+    if reversed.as_slice() < stops { reversed } else { stops.to_vec() }
+}
+
+// share of the edges the two routes do not have in common, 0 for the same route, 1 for routes without a common edge
+pub fn edge_distance(a: &[(usize, usize)], b: &[(usize, usize)]) -> f64 {
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    let union = a.len() + b.len() - common;
+    if union == 0 { 0.0 } else { 1.0 - common as f64 / union as f64 }
+}
+
+// how different the routes of a generation are
+#[derive(Clone, Copy, Debug)]
+pub struct Stats {
+    pub distinct: usize,    // routes that are not a copy or a reversed copy of another one
+    pub size: usize,
+    pub mean_distance: f64, // edge distance averaged over all pairs of routes
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} distinct routes, mean edge distance {:.3}", self.distinct, self.size, self.mean_distance)
+    }
+}
+
+pub fn stats(paths: &[Path]) -> Stats {
+    let distinct: HashSet<Vec<usize>> = paths.iter().map(|path| canonical(&path.city_indexes)).collect();
+    let edges: Vec<Vec<(usize, usize)>> = paths.iter().map(|path| edges(&path.city_indexes)).collect();
+    let (mut total, mut pairs) = (0.0, 0);
+    for i in 0..edges.len() {
+        for j in (i + 1)..edges.len() {
+            total += edge_distance(&edges[i], &edges[j]);
+            pairs += 1;
+        }
+    }
+    Stats { distinct: distinct.len(), size: paths.len(), mean_distance: if pairs == 0 { 0.0 } else { total / pairs as f64 } }
+}
+
+// positions of the routes that are a copy or a reversed copy of a route before them
+pub fn copies(paths: &[Path]) -> Vec<usize> {
+    let mut seen = HashSet::new();
+    paths.iter().enumerate()
+        .filter(|(_, path)| !seen.insert(canonical(&path.city_indexes)))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+// deterministic crowding pairs every child with a parent so that the pairs are as close as possible by edge distance,
+// true when the first child belongs to the second parent and the second child to the first one
+pub fn children_cross(children: &[Path; 2], parents: [&Path; 2]) -> bool {
+    let children: Vec<Vec<(usize, usize)>> = children.iter().map(|child| edges(&child.city_indexes)).collect();
+    let parents: Vec<Vec<(usize, usize)>> = parents.iter().map(|parent| edges(&parent.city_indexes)).collect();
+    edge_distance(&children[0], &parents[1]) + edge_distance(&children[1], &parents[0])
+        < edge_distance(&children[0], &parents[0]) + edge_distance(&children[1], &parents[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(stops: &[usize]) -> Path {
+        Path { city_indexes: stops.to_vec(), population: 0, distance: 0, score: 0 }
+    }
+
+    #[test]
+    fn a_tour_and_its_reverse_are_the_same_route() {
+        assert_eq!(edges(&[0, 1, 2, 0]), edges(&[0, 2, 1, 0]));
+        assert_eq!(canonical(&[0, 2, 1, 0]), canonical(&[0, 1, 2, 0]));
+        assert_eq!(copies(&[path(&[0, 1, 2, 0]), path(&[0, 3, 0]), path(&[0, 2, 1, 0])]), [2]);
+    }
+
+    #[test]
+    fn children_are_paired_with_the_closer_parents() {
+        let parents = [&path(&[0, 1, 2, 3, 0]), &path(&[0, 4, 5, 6, 0])];
+        let like_first = path(&[0, 1, 2, 6, 0]);
+        let like_second = path(&[0, 4, 5, 3, 0]);
+        assert!(!children_cross(&[like_first.clone(), like_second.clone()], parents));
+        assert!(children_cross(&[like_second, like_first], parents));
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt::Write;
+use std::io;
+
+use crate::calculations::{Cities, Config, Distances, Path};
+use crate::roads::RoadNetwork;
+use crate::svg;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    GeoJson,
+    Gpx,
+    Kml,
+    Svg,
+}
+
+impl Format {
+    pub fn from_filename(filename: &str) -> Option<Format> {
+        let extension = filename.rsplit('.').next()?.to_lowercase();
+        match extension.as_str() {
+            "geojson" | "json" => Some(Format::GeoJson),
+            "gpx" => Some(Format::Gpx),
+            "kml" => Some(Format::Kml),
+            "svg" => Some(Format::Svg),
+            _ => None,
+        }
+    }
+}
+
+// one visited city of the route, in driving order
+struct Stop<'a> {
+    order: usize,
+    city: usize,
+    name: &'a str,
+    population: u32,
+    coords: (f32, f32),
+    cumulative_km: u32,
+}
+
+fn stops<'a>(path: &Path, cities: &'a Cities, distances: &Distances) -> Vec<Stop<'a>> {
+    let mut cumulative_km = 0;
+    path.city_indexes.iter().enumerate().map(|(order, &city)| {
+        if order > 0 {
+            cumulative_km += distances[path.city_indexes[order - 1]][city];
+        }
+        Stop { order, city, name: &cities.names[city], population: cities.populations[city], coords: cities.coords[city], cumulative_km }
+    }).collect()
+}
+
+// the drawn line follows the roads when they are known, otherwise it connects the cities directly
+fn route_line(path: &Path, cities: &Cities, roads: Option<&RoadNetwork>) -> Vec<(f32, f32)> {
+    match roads {
+        Some(roads) => {
+            let mut line: Vec<(f32, f32)> = Vec::new();
+            for leg in roads.leg_polylines(path) {
+                let skip = usize::from(!line.is_empty()); // every leg starts where the previous one ended
+                line.extend(leg.into_iter().skip(skip));
+            }
+            line
+        }
+        None => path.city_indexes.iter().map(|&city| cities.coords[city]).collect(),
+    }
+}
+
+fn escape_json(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub fn route_geojson(path: &Path, cities: &Cities, distances: &Distances, roads: Option<&RoadNetwork>) -> String {
+    let mut features = Vec::new();
+    for stop in stops(path, cities, distances) {
+        features.push(format!(
+            "{{\"type\":\"Feature\",\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},{}]}},\"properties\":{{\"order\":{},\"index\":{},\"name\":\"{}\",\"population\":{},\"cumulative_km\":{}}}}}",
+            stop.coords.0, stop.coords.1, stop.order, stop.city, escape_json(stop.name), stop.population, stop.cumulative_km
+        ));
+    }
+    let coordinates: Vec<String> = route_line(path, cities, roads).iter().map(|(lon, lat)| format!("[{},{}]", lon, lat)).collect();
+    features.push(format!(
+        "{{\"type\":\"Feature\",\"geometry\":{{\"type\":\"LineString\",\"coordinates\":[{}]}},\"properties\":{{\"score\":{},\"population\":{},\"distance_km\":{}}}}}",
+        coordinates.join(","), path.score, path.population, path.distance
+    ));
+    format!("{{\"type\":\"FeatureCollection\",\"features\":[\n{}\n]}}\n", features.join(",\n"))
+}
+
+pub fn route_gpx(path: &Path, cities: &Cities, distances: &Distances, roads: Option<&RoadNetwork>) -> String {
+    let mut gpx = String::new();
+    gpx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    gpx.push_str("<gpx version=\"1.1\" creator=\"gelnica-chopper\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n");
+    let stops = stops(path, cities, distances);
+    for stop in &stops {
+        let _ = writeln!(gpx, "  <wpt lat=\"{}\" lon=\"{}\"><name>{}</name><desc>population {}, {} km</desc></wpt>",
+            stop.coords.1, stop.coords.0, escape_xml(stop.name), stop.population, stop.cumulative_km);
+    }
+    // the route is what navigation devices follow, the track is the line to draw
+    let _ = writeln!(gpx, "  <rte><name>Score {}, population {}, {} km</name>", path.score, path.population, path.distance);
+    for stop in &stops {
+        let _ = writeln!(gpx, "    <rtept lat=\"{}\" lon=\"{}\"><name>{}</name></rtept>", stop.coords.1, stop.coords.0, escape_xml(stop.name));
+    }
+    gpx.push_str("  </rte>\n  <trk><name>Route</name><trkseg>\n");
+    for (lon, lat) in route_line(path, cities, roads) {
+        let _ = writeln!(gpx, "    <trkpt lat=\"{}\" lon=\"{}\"/>", lat, lon);
+    }
+    gpx.push_str("  </trkseg></trk>\n</gpx>\n");
+    gpx
+}
+
+pub fn route_kml(path: &Path, cities: &Cities, distances: &Distances, roads: Option<&RoadNetwork>) -> String {
+    let mut kml = String::new();
+    kml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    kml.push_str("<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n");
+    let _ = writeln!(kml, "  <name>Score {}, population {}, {} km</name>", path.score, path.population, path.distance);
+    for stop in stops(path, cities, distances) {
+        let _ = writeln!(kml, "  <Placemark><name>{}</name><ExtendedData><Data name=\"order\"><value>{}</value></Data><Data name=\"population\"><value>{}</value></Data><Data name=\"cumulative_km\"><value>{}</value></Data></ExtendedData><Point><coordinates>{},{}</coordinates></Point></Placemark>",
+            escape_xml(stop.name), stop.order, stop.population, stop.cumulative_km, stop.coords.0, stop.coords.1);
+    }
+    let coordinates: Vec<String> = route_line(path, cities, roads).iter().map(|(lon, lat)| format!("{},{}", lon, lat)).collect();
+    let _ = writeln!(kml, "  <Placemark><name>Route</name><LineString><tessellate>1</tessellate><coordinates>{}</coordinates></LineString></Placemark>", coordinates.join(" "));
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+pub fn write_route(filename: &str, format: Format, path: &Path, cities: &Cities, distances: &Distances, config: &Config, roads: Option<&RoadNetwork>) -> io::Result<()> {
+    let content = match format {
+        Format::GeoJson => route_geojson(path, cities, distances, roads),
+        Format::Gpx => route_gpx(path, cities, distances, roads),
+        Format::Kml => route_kml(path, cities, distances, roads),
+        Format::Svg => svg::render_route(path, cities, distances, config, roads),
+    };
+    std::fs::write(filename, content)
+}
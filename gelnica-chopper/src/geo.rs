@@ -0,0 +1,41 @@
+use std::error::Error;
+use csv::Writer;
+
+use crate::calculations::{Cities, Distances};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+// reverse_gelnica/city_distance_matrix.csv is itself made of great-circle distances (median ratio 0.999 over all pairs),
+// so the estimate matches it by default, road-like distances need a factor over 1 (--detour)
+pub const DETOUR_FACTOR: f64 = 1.0;
+
+// great-circle distance between two (longitude, latitude) points
+pub fn haversine_km(from: (f32, f32), to: (f32, f32)) -> f64 {
+    let (lon1, lat1) = (f64::from(from.0).to_radians(), f64::from(from.1).to_radians());
+    let (lon2, lat2) = (f64::from(to.0).to_radians(), f64::from(to.1).to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+// estimates the road distance between every pair of cities from their coordinates
+pub fn distances_from_coords(cities: &Cities, detour_factor: f64) -> Distances {
+    cities.coords.iter().map(|&from| {
+        cities.coords.iter()
+            .map(|&to| (haversine_km(from, to) * detour_factor).round() as u32)
+            .collect()
+    }).collect()
+}
+
+// writes the matrix in the same layout as city_distance_matrix.csv (city names as header and first column)
+pub fn write_distances(filename: &str, cities: &Cities, distances: &Distances) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_path(filename)?;
+    let mut header = vec![String::new()];
+    header.extend(cities.names.iter().cloned());
+    wtr.write_record(&header)?;
+    for (name, row) in cities.names.iter().zip(distances) {
+        let mut record = vec![name.clone()];
+        record.extend(row.iter().map(|distance| distance.to_string()));
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
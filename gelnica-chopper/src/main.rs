@@ -1,29 +1,35 @@
-use macroquad::prelude::{clear_background, next_frame, Conf, draw_text, draw_rectangle, draw_line, WHITE, BLACK, RED, BLUE};
-use rand::Rng;
-use rand::rngs::ThreadRng;
-use std::io::{self, Write};
+use macroquad::prelude::{clear_background, next_frame, Conf, draw_text, draw_line, WHITE, BLACK};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+mod adaptive;
+mod archive;
+mod cache;
 mod calculations;
+mod construct;
+mod coverage;
+mod data;
+mod diff;
+mod diversity;
+mod export;
+mod geo;
+mod moves;
+mod params;
+mod pareto;
+mod results;
+mod roads;
+mod segments;
+mod svg;
+mod sweep;
+mod timing;
+mod trip;
+mod tuning;
+mod value;
 
-const X_MIN: f32 = 17.0;
-const X_MAX: f32 = 22.5;
-const Y_MIN: f32 = 47.5;
-const Y_MAX: f32 = 49.5;
-
-// Screen size
-const SCREEN_WIDTH: f32 = 1800.0;
-const SCREEN_HEIGHT: f32 = 800.0;
-
-fn scale_x(x: f32) -> f32 {
-    ((x - X_MIN) / (X_MAX - X_MIN)) * SCREEN_WIDTH
-}
-
-fn scale_y(y: f32) -> f32 {
-    SCREEN_HEIGHT - ((y - Y_MIN) / (Y_MAX - Y_MIN)) * SCREEN_HEIGHT
-}
+use svg::{scale_x, scale_y, SCREEN_WIDTH, SCREEN_HEIGHT};
 
 fn window_conf() -> Conf {
     Conf {
@@ -34,13 +40,23 @@ fn window_conf() -> Conf {
     }
 }
 
-fn draw_path(path: &calculations::Path, cities: &calculations::Cities) {
+fn draw_path(path: &calculations::Path, cities: &calculations::Cities, roads: Option<&roads::RoadNetwork>) {
+    let leg_polylines = roads.map(|roads| roads.leg_polylines(path));
     for i in 0..path.city_indexes.len() - 1 {
         let first_city_index = path.city_indexes[i];
         let second_city_index = path.city_indexes[i + 1];
         let (x1, y1) = cities.coords[first_city_index];
         let (x2, y2) = cities.coords[second_city_index];
-        draw_line(scale_x(x1), scale_y(y1), scale_x(x2), scale_y(y2), 2.0, BLACK);
+        match &leg_polylines {
+            // follow the road instead of the straight line
+            Some(polylines) => {
+                for segment in polylines[i].windows(2) {
+                    let ((sx1, sy1), (sx2, sy2)) = (segment[0], segment[1]);
+                    draw_line(scale_x(sx1), scale_y(sy1), scale_x(sx2), scale_y(sy2), 2.0, BLACK);
+                }
+            }
+            None => draw_line(scale_x(x1), scale_y(y1), scale_x(x2), scale_y(y2), 2.0, BLACK),
+        }
 
         let caption = format!("{}({})", cities.names[first_city_index].clone(), cities.populations[first_city_index].clone());
         draw_text(
@@ -53,15 +69,738 @@ fn draw_path(path: &calculations::Path, cities: &calculations::Cities) {
     }
 }
 
-fn main_battle() {
-    calculations::battle_royale();
+// everything the optimizer needs, built from the common command line options
+struct Setup {
+    cities: calculations::Cities,
+    distances: calculations::Distances,
+    roads: Option<roads::RoadNetwork>,
+    config: calculations::Config,
 }
 
-async fn main_with_ui() {
-    let cities = calculations::load_cities("gelnica-chopper/cities.csv").unwrap();
-    let distances = calculations::load_distances("gelnica-chopper/city_distance_matrix.csv").unwrap();
+// --cities <file> --distances <file> [--detour <factor>] [roads] [--drive-through] [--seed-routes <file> [--seed-mutations]] [--init <mix>] [--days <limits>]
+// [--max-distance <km|none>] [--speed <km/h>] [--time-matrix <file>] [--dwell <minutes>] [--max-hours <hours>]
+// [--start <time>] [--windows <file>] [--require <cities>] [--avoid <cities>] [--bad-cities <cities|none>] [--start-city <city>] [--end-city <city|any>]
+// [--value <model>] [--region-bonus <points>] [--district-bonus <points>] [--min-per-region <count>] [--max-per-district <count>]
+// [--ga <file>] [--adaptive-mutation] [--diversity] [--local-search] [--no-cache]
+fn load_setup(args: &mut Vec<String>) -> Setup {
+    let cities_file = take_option(args, "--cities").unwrap_or(data::CITIES_FILE.to_string());
+    let distances_file = take_option(args, "--distances").unwrap_or(data::DISTANCES_FILE.to_string());
+    let detour_factor = take_detour(args);
+    let drive_through = take_flag(args, "--drive-through");
+    let seed_routes_file = take_option(args, "--seed-routes");
+    let seed_mutations = take_flag(args, "--seed-mutations");
+    let initializers = take_option(args, "--init");
+    let day_limits = take_option(args, "--days");
+    let max_distance = take_option(args, "--max-distance");
+    let speed = take_option(args, "--speed");
+    let time_matrix_file = take_option(args, "--time-matrix");
+    let dwell = take_option(args, "--dwell");
+    let max_hours = take_option(args, "--max-hours");
+    let start = take_option(args, "--start");
+    let windows_file = take_option(args, "--windows");
+    let required = take_option(args, "--require");
+    let avoided = take_option(args, "--avoid");
+    let bad_cities = take_option(args, "--bad-cities");
+    let start_city = take_option(args, "--start-city");
+    let end_city = take_option(args, "--end-city");
+    let value_model = take_option(args, "--value");
+    let region_bonus = take_option(args, "--region-bonus");
+    let district_bonus = take_option(args, "--district-bonus");
+    let min_per_region = take_option(args, "--min-per-region");
+    let max_per_district = take_option(args, "--max-per-district");
+    let ga_file = take_option(args, "--ga");
+    let adaptive_mutation = take_flag(args, "--adaptive-mutation");
+    let diversity = take_flag(args, "--diversity");
+    let local_search = take_flag(args, "--local-search");
+    let no_cache = take_flag(args, "--no-cache");
+
+    let cities = loaded(data::load_cities(&cities_file));
+    let roads = load_roads(args, &cities);
+    let distances = match &roads {
+        Some(roads) => roads.routes.distances.clone(),
+        None => loaded(data::load_dataset(&cities_file, &distances_file, detour_factor)).1,
+    };
+
+    let mut config = calculations::Config::default();
+    if drive_through {
+        let Some(roads) = &roads else {
+            println!("--drive-through needs a road network (--osm or --nodes/--edges)");
+            std::process::exit(2);
+        };
+        config.drive_through = Some(Arc::new(roads.routes.drive_through()));
+    }
+    if let Some(seed_routes_file) = seed_routes_file {
+        let text = std::fs::read_to_string(&seed_routes_file).unwrap_or_else(|error| {
+            println!("--seed-routes {}: {}", seed_routes_file, error);
+            std::process::exit(2);
+        });
+        for route in results::parse_routes(&text) {
+            if route.city_indexes.iter().any(|&city| city >= cities.names.len()) {
+                println!("Skipping seed route from {}:{}, unknown city index", seed_routes_file, route.line);
+                continue;
+            }
+            config.seed_routes.push(route.city_indexes);
+        }
+        println!("Loaded {} seed route(s) from {}", config.seed_routes.len(), seed_routes_file);
+    }
+    config.seed_mutations = seed_mutations;
+    if let Some(initializers) = initializers {
+        config.initializers = construct::InitializerMix::parse(&initializers).unwrap_or_else(|error| {
+            println!("--init: {}", error);
+            std::process::exit(2);
+        });
+    }
+    if let Some(day_limits) = day_limits {
+        config.day_limits = trip::parse_day_limits(&day_limits).unwrap_or_else(|error| {
+            println!("--days: {}", error);
+            std::process::exit(2);
+        });
+    }
+    if let Some(max_distance) = max_distance {
+        config.max_distance = match max_distance.as_str() {
+            "none" => None,
+            value => Some(value.parse().unwrap_or_else(|_| {
+                println!("--max-distance expects kilometres or none");
+                std::process::exit(2);
+            })),
+        };
+    }
+    if speed.is_some() || time_matrix_file.is_some() || dwell.is_some() || max_hours.is_some() || start.is_some() || windows_file.is_some() {
+        let mut time = timing::TimeModel::default();
+        if let Some(speed) = speed {
+            time.speed = speed.parse().ok().filter(|&speed: &f64| speed > 0.0).unwrap_or_else(|| {
+                println!("--speed expects km/h, e.g. --speed 65");
+                std::process::exit(2);
+            });
+        }
+        if let Some(time_matrix_file) = time_matrix_file {
+            time.matrix = Some(loaded(data::load_distances(&time_matrix_file, &cities)));
+        }
+        if let Some(dwell) = dwell {
+            time.dwell = dwell.parse().unwrap_or_else(|_| {
+                println!("--dwell expects minutes, e.g. --dwell 20");
+                std::process::exit(2);
+            });
+        }
+        if let Some(max_hours) = max_hours {
+            time.budget = Some(timing::parse_hours(&max_hours).unwrap_or_else(|error| {
+                println!("--max-hours: {}", error);
+                std::process::exit(2);
+            }));
+        }
+        if let Some(start) = start {
+            time.start = timing::parse_hours(&start).unwrap_or_else(|error| {
+                println!("--start: {}", error);
+                std::process::exit(2);
+            });
+        }
+        if let Some(windows_file) = windows_file {
+            time.windows = loaded(data::load_windows(&windows_file, &cities));
+            println!("Loaded opening hours of {} cities from {}", time.windows.iter().flatten().count(), windows_file);
+        }
+        config.time = Some(Arc::new(time));
+    }
+    for (option, value, list) in [("--require", required, &mut config.required), ("--avoid", avoided, &mut config.avoided)] {
+        if let Some(value) = value {
+            *list = parse_city_names(&value, &cities).unwrap_or_else(|error| {
+                println!("{}: {}", option, error);
+                std::process::exit(2);
+            });
+        }
+    }
+    // the defaults are indexes into the reference cities.csv, another dataset has to name its own cities
+    match bad_cities.as_deref() {
+        Some("none") => config.bad_cities.clear(),
+        Some(value) => {
+            config.bad_cities = parse_city_names(value, &cities).unwrap_or_else(|error| {
+                println!("--bad-cities: {}", error);
+                std::process::exit(2);
+            });
+            config.bad_cities.sort_unstable();
+            config.bad_cities.dedup();
+        }
+        None => {
+            if config.bad_cities.iter().any(|&city| city >= cities.names.len()) {
+                println!("{} has {} cities, fewer than the default bad cities need, give them with --bad-cities <cities|none>", cities_file, cities.names.len());
+                std::process::exit(2);
+            }
+        }
+    }
+    if start_city.is_none() && calculations::HOME_CITY >= cities.names.len() {
+        println!("{} has {} cities, the default start city {} is not one of them, choose one with --start-city", cities_file, cities.names.len(), calculations::HOME_CITY);
+        std::process::exit(2);
+    }
+    if let Some(start_city) = start_city {
+        config.start = single_city("--start-city", &start_city, &cities);
+        // a loop stays a loop unless the end is given as well
+        if config.end == Some(calculations::HOME_CITY) {
+            config.end = Some(config.start);
+        }
+    }
+    if let Some(end_city) = end_city {
+        config.end = if end_city == "any" { None } else { Some(single_city("--end-city", &end_city, &cities)) };
+    }
+    if let Some(value_model) = value_model {
+        config.value = value::ValueModel::parse(&value_model).unwrap_or_else(|error| {
+            println!("--value: {}", error);
+            std::process::exit(2);
+        });
+        if config.value == value::ValueModel::Custom && cities.values.iter().all(Option::is_none) {
+            println!("--value custom needs a value column in {}", cities_file);
+            std::process::exit(2);
+        }
+    }
+    for (option, value, bonus, grouping) in [("--region-bonus", region_bonus, &mut config.region_bonus, &cities.regions),
+                                             ("--district-bonus", district_bonus, &mut config.district_bonus, &cities.districts)] {
+        if let Some(value) = value {
+            *bonus = value.parse().unwrap_or_else(|_| {
+                println!("{} expects a number", option);
+                std::process::exit(2);
+            });
+            if grouping.names.is_empty() {
+                println!("Warning: {} has no effect, {} has no such column", option, cities_file);
+            }
+        }
+    }
+    if let Some(min_per_region) = min_per_region {
+        config.min_per_region = min_per_region.parse().unwrap_or_else(|_| {
+            println!("--min-per-region expects a number of cities");
+            std::process::exit(2);
+        });
+        if cities.regions.names.is_empty() {
+            println!("Warning: --min-per-region has no effect, {} has no region column", cities_file);
+        }
+    }
+    if let Some(max_per_district) = max_per_district {
+        config.max_per_district = Some(max_per_district.parse().ok().filter(|&max| max > 0).unwrap_or_else(|| {
+            println!("--max-per-district expects a number of cities, at least 1");
+            std::process::exit(2);
+        }));
+        if cities.districts.names.is_empty() {
+            println!("Warning: --max-per-district has no effect, {} has no district column", cities_file);
+        }
+    }
+    if let Some(ga_file) = ga_file {
+        let text = std::fs::read_to_string(&ga_file).unwrap_or_else(|error| {
+            println!("--ga {}: {}", ga_file, error);
+            std::process::exit(2);
+        });
+        config.ga = params::GaParams::parse(&text).unwrap_or_else(|error| {
+            println!("--ga {}: {}", ga_file, error);
+            std::process::exit(2);
+        });
+    }
+    config.adaptive_mutation = adaptive_mutation;
+    config.diversity = diversity;
+    config.local_search = local_search;
+    config.score_cache = !no_cache;
+    if local_search && moves::Scoring::new(&cities, &distances, &config).is_none() {
+        println!("Warning: --local-search has no effect with time, days, driving through, coverage bonuses or coverage limits");
+    }
+    if let Some(city) = config.required.iter().find(|city| config.avoided.contains(city)) {
+        println!("{} is both required and avoided", cities.names[*city]);
+        std::process::exit(2);
+    }
+    Setup { cities, distances, roads, config }
+}
+
+// prints the cities that were visited without stopping there
+fn print_driven_through(path: &calculations::Path, setup: &Setup) {
+    let picked_up = calculations::driven_through(path, &setup.config);
+    if !picked_up.is_empty() {
+        let names: Vec<String> = picked_up.iter().map(|&city| format!("{}({})", setup.cities.names[city], city)).collect();
+        println!("Driven through: {}", names.join(", "));
+    }
+}
 
+// parses and scores a route, None if it is empty or refers to unknown cities
+// missing required cities are inserted where they cost the least
+fn scored_path(text: &str, setup: &Setup) -> Option<calculations::Path> {
+    let city_indexes = calculations::parse_city_indexes(text);
+    if city_indexes.is_empty() || city_indexes.iter().any(|&city| city >= setup.cities.names.len()) {
+        return None;
+    }
     let mut path = calculations::Path {
+        city_indexes,
+        population: 0,
+        distance: 0,
+        score: 0,
+    };
+    let missing: Vec<usize> = setup.config.required.iter().copied().filter(|city| !path.city_indexes.contains(city)).collect();
+    if !missing.is_empty() {
+        calculations::insert_required(&mut path, &setup.distances, &setup.config);
+        println!("Inserted required cities: {}", city_list(&missing, setup));
+    }
+    calculations::calculate_score(&mut path, &setup.cities, &setup.distances, &setup.config);
+    Some(path)
+}
+
+// one city name or index given to the option, exits when it is not known
+fn single_city(option: &str, text: &str, cities: &calculations::Cities) -> usize {
+    match parse_city_names(text, cities).as_deref() {
+        Ok([city]) => *city,
+        Ok(_) => {
+            println!("{} expects a single city", option);
+            std::process::exit(2);
+        }
+        Err(error) => {
+            println!("{}: {}", option, error);
+            std::process::exit(2);
+        }
+    }
+}
+
+// "Poprad,2,Košice": city names or indexes separated by commas
+fn parse_city_names(text: &str, cities: &calculations::Cities) -> Result<Vec<usize>, String> {
+    text.split(',').map(|part| {
+        let part = part.trim();
+        match part.parse::<usize>() {
+            Ok(city) if city < cities.names.len() => Ok(city),
+            Ok(city) => Err(format!("there is no city {}", city)),
+            Err(_) => cities.names.iter().position(|name| name == part).ok_or(format!("unknown city '{}'", part)),
+        }
+    }).collect()
+}
+
+// --export <file.geojson|file.gpx|file.kml|file.svg>
+fn take_export_option(args: &mut Vec<String>) -> Option<String> {
+    let filename = take_option(args, "--export")?;
+    if export::Format::from_filename(&filename).is_none() {
+        println!("Unknown export format of {}, use .geojson, .gpx, .kml or .svg", filename);
+        std::process::exit(2);
+    }
+    Some(filename)
+}
+
+fn export_path(filename: &str, path: &calculations::Path, setup: &Setup) {
+    let format = export::Format::from_filename(filename).unwrap();
+    match export::write_route(filename, format, path, &setup.cities, &setup.distances, &setup.config, setup.roads.as_ref()) {
+        Ok(()) => println!("Route exported to {}", filename),
+        Err(error) => println!("Could not export route to {}: {}", filename, error),
+    }
+}
+
+// one line per stop with the distance driven so far and, with a time model, the arrival time
+// on a multi-day trip every day starts with a summary
+fn print_itinerary(path: &calculations::Path, setup: &Setup) {
+    let days = trip::split_days(path, &setup.cities, &setup.distances, &setup.config.day_limits);
+    let visits = setup.config.time.as_ref().map(|time| time.visits(&path.city_indexes, &setup.distances));
+    let mut visited = std::collections::HashSet::new();
+    let mut cumulative_km = 0;
+    for (order, &city) in path.city_indexes.iter().enumerate() {
+        if order > 0 {
+            cumulative_km += setup.distances[path.city_indexes[order - 1]][city];
+        }
+        if let Some((number, day)) = days.iter().enumerate().find(|(_, day)| day.start == order) {
+            let first = if number == 0 { day.start } else { day.start + 1 };
+            let population: u32 = path.city_indexes[first..=day.end].iter()
+                .filter(|&&city| visited.insert(city))
+                .map(|&city| setup.cities.populations[city])
+                .sum();
+            println!("Day {}: {} -> {}, {} of {} km{}, population {}", number + 1,
+                setup.cities.names[path.city_indexes[day.start]], setup.cities.names[path.city_indexes[day.end]],
+                day.distance, day.limit, if day.distance > day.limit { " (over the limit)" } else { "" }, population);
+        }
+        // arrival as the time of day, then the wait for the city to open or how late it was reached
+        let arrival = match (&setup.config.time, &visits) {
+            (Some(time), Some(visits)) => {
+                let visit = visits[order];
+                let note = if visit.late > 0 {
+                    format!("late {}", timing::format_minutes(visit.late))
+                } else if visit.wait > 0 {
+                    format!("wait {}", timing::format_minutes(visit.wait))
+                } else {
+                    String::new()
+                };
+                format!("{:>6}  {:<10}  ", timing::format_minutes(time.start + visit.arrival), note)
+            }
+            _ => String::new(),
+        };
+        println!("{:>4} km  {}{:>3}  {} ({})", cumulative_km, arrival, city, setup.cities.names[city], setup.cities.populations[city]);
+    }
+    if let (Some(time), Some(visits)) = (&setup.config.time, &visits) {
+        let total = visits.last().map_or(0, |visit| visit.arrival);
+        match time.budget {
+            Some(budget) if total > budget => println!("Total time: {} of {} (over the budget)", timing::format_minutes(total), timing::format_minutes(budget)),
+            Some(budget) => println!("Total time: {} of {}", timing::format_minutes(total), timing::format_minutes(budget)),
+            None => println!("Total time: {}", timing::format_minutes(total)),
+        }
+    }
+    print_driven_through(path, setup);
+    print_values(path, setup);
+}
+
+// the full itinerary when the route line alone does not tell enough, i.e. on multi-day or timed trips
+fn print_route_details(path: &calculations::Path, setup: &Setup) {
+    if setup.config.day_limits.is_empty() && setup.config.time.is_none() {
+        print_driven_through(path, setup);
+        print_values(path, setup);
+    } else {
+        print_itinerary(path, setup);
+    }
+}
+
+// worth of the route in every value model, the one used for scoring is marked
+fn print_values(path: &calculations::Path, setup: &Setup) {
+    let visited = calculations::visited_cities(path, &setup.config);
+    let has_values = setup.cities.values.iter().any(Option::is_some);
+    let mut models = value::MODELS.to_vec();
+    if !models.contains(&setup.config.value) {
+        models.push(setup.config.value);
+    }
+    let values: Vec<String> = models.iter()
+        .filter(|&&model| model != value::ValueModel::Custom || has_values)
+        .map(|model| format!("{}{} {}", if *model == setup.config.value { "*" } else { "" }, model, model.route_value(&setup.cities, &visited)))
+        .collect();
+    println!("Value: {}", values.join(", "));
+    let mut coverage = Vec::new();
+    for (what, grouping) in [("regions", &setup.cities.regions), ("districts", &setup.cities.districts)] {
+        if !grouping.names.is_empty() {
+            coverage.push(format!("{} {}/{}", what, coverage::covered(grouping, &visited), grouping.names.len()));
+        }
+    }
+    if !coverage.is_empty() {
+        println!("Covered: {}", coverage.join(", "));
+    }
+    print_coverage(&visited, setup);
+}
+
+// visited cities and population of every region, and the districts over their limit
+fn print_coverage(visited: &std::collections::HashSet<usize>, setup: &Setup) {
+    let cities = &setup.cities;
+    let mut sorted: Vec<usize> = visited.iter().copied().collect();
+    sorted.sort_by_key(|&city| std::cmp::Reverse(cities.populations[city]));
+    for (region, name) in cities.regions.names.iter().enumerate() {
+        let in_region: Vec<usize> = sorted.iter().copied().filter(|&city| cities.regions.of_city[city] == Some(region)).collect();
+        let population: u32 = in_region.iter().map(|&city| cities.populations[city]).sum();
+        let names: Vec<&str> = in_region.iter().map(|&city| cities.names[city].as_str()).collect();
+        let short = if (in_region.len() as u32) < setup.config.min_per_region { " (below the minimum)" } else { "" };
+        println!("  {}: {} cities, population {}{}  {}", name, in_region.len(), population, short, names.join(", "));
+    }
+    if let Some(max) = setup.config.max_per_district {
+        let counts = coverage::group_counts(&cities.districts, visited);
+        let over: Vec<String> = counts.iter().enumerate()
+            .filter(|&(_, &count)| count > max)
+            .map(|(district, count)| format!("{} ({})", cities.districts.names[district], count))
+            .collect();
+        if !over.is_empty() {
+            println!("Districts over the limit of {}: {}", max, over.join(", "));
+        }
+    }
+}
+
+fn print_entry(entry: &archive::Entry) {
+    println!("#{:<4} score {:>8}  population {:>8}  distance {:>5}  stops {:>3}  seed {:<20}  {}",
+        entry.id, entry.score, entry.population, entry.distance, entry.city_indexes.len(),
+        entry.seed.map_or("-".to_string(), |seed| seed.to_string()), entry.source);
+}
+
+// archive import <files...> | list | best [count] | show <id>
+fn main_archive(mut args: Vec<String>) {
+    let archive_file = take_option(&mut args, "--archive").unwrap_or(archive::ARCHIVE_FILE.to_string());
+    let command = if args.is_empty() { String::new() } else { args.remove(0) };
+    match command.as_str() {
+        "import" => {
+            let setup = load_setup(&mut args);
+            let (mut added, mut skipped) = (0, 0);
+            for filename in &args {
+                let text = std::fs::read_to_string(filename).unwrap();
+                for route in results::parse_routes(&text) {
+                    let source = format!("{}:{}", filename, route.line);
+                    if route.city_indexes.iter().any(|&city| city >= setup.cities.names.len()) {
+                        println!("Skipping {}: unknown city index", source);
+                        skipped += 1;
+                        continue;
+                    }
+                    // the route as logged, re-scored with the current rules since the score in the file may be outdated,
+                    // but not repaired: missing required cities are penalized instead of inserted
+                    let mut path = calculations::Path { city_indexes: route.city_indexes, population: 0, distance: 0, score: 0 };
+                    calculations::calculate_score(&mut path, &setup.cities, &setup.distances, &setup.config);
+                    let config = route.config.unwrap_or(setup.config.to_string());
+                    match archive::add(&archive_file, &path, &source, route.seed, Some(config)).unwrap() {
+                        Some(entry) => {
+                            print_entry(&entry);
+                            added += 1;
+                        }
+                        None => skipped += 1,
+                    }
+                }
+            }
+            println!("Archived {} route(s), skipped {}", added, skipped);
+        }
+        "list" => {
+            for entry in archive::load(&archive_file).unwrap() {
+                print_entry(&entry);
+            }
+        }
+        "best" => {
+            let count = args.first().map_or(10, |count| count.parse().unwrap_or_else(|_| {
+                println!("archive best expects a number of routes, got '{}'", count);
+                std::process::exit(2);
+            }));
+            let entries = archive::load(&archive_file).unwrap();
+            for entry in archive::best(&entries, count) {
+                print_entry(entry);
+            }
+        }
+        "show" => {
+            let id: Option<usize> = args.first().and_then(|id| id.parse().ok());
+            let entries = archive::load(&archive_file).unwrap();
+            let Some(entry) = entries.iter().find(|entry| Some(entry.id) == id) else {
+                println!("No archived route with id {:?}", args.first());
+                std::process::exit(2);
+            };
+            args.remove(0);
+            let setup = load_setup(&mut args);
+            print_entry(entry);
+            if let Some(config) = &entry.config {
+                println!("Config: {}", config);
+            }
+            println!("{}", entry.path());
+            print_itinerary(&entry.path(), &setup);
+        }
+        _ => {
+            println!("Usage: archive [--archive file] import <files...> | list | best [count] | show <id>");
+            std::process::exit(2);
+        }
+    }
+}
+
+// segments <files...> [--min-length N] [--top N] [--blocks file]
+fn main_segments(mut args: Vec<String>) {
+    let min_length = take_number(&mut args, "--min-length", 3);
+    let top = take_number(&mut args, "--top", 20);
+    let blocks_file = take_option(&mut args, "--blocks");
+    let cities_file = take_option(&mut args, "--cities").unwrap_or(data::CITIES_FILE.to_string());
+    let cities = loaded(data::load_cities(&cities_file));
+
+    // the same route copied twice (or reversed) would count as agreement
+    let mut routes: Vec<Vec<usize>> = Vec::new();
+    for filename in &args {
+        let text = std::fs::read_to_string(filename).unwrap();
+        for route in results::parse_routes(&text) {
+            let reversed: Vec<usize> = route.city_indexes.iter().rev().copied().collect();
+            if !routes.contains(&route.city_indexes) && !routes.contains(&reversed) {
+                routes.push(route.city_indexes);
+            }
+        }
+    }
+    if routes.len() < 2 {
+        println!("Need at least two different routes, found {}", routes.len());
+        std::process::exit(2);
+    }
+
+    let segments = segments::common_segments(&routes, min_length);
+    println!("{} common segments of at least {} cities in {} routes", segments.len(), min_length, routes.len());
+    for segment in segments.iter().take(top) {
+        let names: Vec<&str> = segment.cities.iter().map(|&city| cities.names[city].as_str()).collect();
+        println!("{:>3}/{} routes  {:?}  {}", segment.routes.len(), routes.len(), segment.cities, names.join(" - "));
+    }
+
+    // one block per line, in the same format as the bare lists in results.txt
+    if let Some(blocks_file) = blocks_file {
+        let lines: Vec<String> = segments.iter().take(top)
+            .map(|segment| segment.cities.iter().map(|city| city.to_string()).collect::<Vec<String>>().join(", "))
+            .collect();
+        std::fs::write(&blocks_file, lines.join("\n") + "\n").unwrap();
+        println!("Wrote {} building blocks to {}", lines.len(), blocks_file);
+    }
+}
+
+// @<id> is an archived route, an existing file gives its last route (the final result of a run),
+// anything else is read as the route itself
+fn resolve_route(argument: &str, archive_file: &str, setup: &Setup) -> calculations::Path {
+    let text = if let Some(id) = argument.strip_prefix('@') {
+        let entries = archive::load(archive_file).unwrap();
+        match entries.iter().find(|entry| entry.id.to_string() == id) {
+            Some(entry) => format!("{:?}", entry.city_indexes),
+            None => {
+                println!("No archived route with id {}", id);
+                std::process::exit(2);
+            }
+        }
+    } else if std::path::Path::new(argument).is_file() {
+        let routes = results::parse_routes(&std::fs::read_to_string(argument).unwrap());
+        match routes.last() {
+            Some(route) => format!("{:?}", route.city_indexes),
+            None => {
+                println!("No route found in {}", argument);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        argument.to_string()
+    };
+    match scored_path(&text, setup) {
+        Some(path) => path,
+        None => {
+            println!("Invalid route: {}", argument);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn city_list(cities: &[usize], setup: &Setup) -> String {
+    let names: Vec<String> = cities.iter().map(|&city| format!("{}({})", setup.cities.names[city], city)).collect();
+    if names.is_empty() { "-".to_string() } else { names.join(", ") }
+}
+
+fn leg_list(legs: &[(usize, usize)], setup: &Setup) -> String {
+    let names: Vec<String> = legs.iter()
+        .map(|&(from, to)| format!("{}-{} ({} km)", setup.cities.names[from], setup.cities.names[to], setup.distances[from][to]))
+        .collect();
+    if names.is_empty() { "-".to_string() } else { names.join(", ") }
+}
+
+// diff <A> <B> [--svg file] [options]
+fn main_diff(mut args: Vec<String>) {
+    let archive_file = take_option(&mut args, "--archive").unwrap_or(archive::ARCHIVE_FILE.to_string());
+    let svg_file = take_option(&mut args, "--svg");
+    let setup = load_setup(&mut args);
+    if args.len() != 2 {
+        println!("Usage: diff <A> <B> [--svg file] [options], a route is @<archive id>, a results/log file or a list of cities");
+        std::process::exit(2);
+    }
+    let a = resolve_route(&args[0], &archive_file, &setup);
+    let b = resolve_route(&args[1], &archive_file, &setup);
+    let route_diff = diff::diff_routes(&a, &b);
+
+    println!("A: {}", a);
+    println!("B: {}", b);
+    println!("Only in A: {}", city_list(&route_diff.only_in_a, &setup));
+    println!("Only in B: {}", city_list(&route_diff.only_in_b, &setup));
+    println!("Legs only in A: {}", leg_list(&route_diff.legs_only_in_a, &setup));
+    println!("Legs only in B: {}", leg_list(&route_diff.legs_only_in_b, &setup));
+    println!("Reordered{}: {}", if route_diff.b_reversed { " (B driven in reverse)" } else { "" }, city_list(&route_diff.reordered, &setup));
+    println!("Change B - A: score {:+}, population {:+}, distance {:+} km",
+        route_diff.score_change, route_diff.population_change, route_diff.distance_change);
+
+    if let Some(svg_file) = svg_file {
+        std::fs::write(&svg_file, svg::render_diff(&a, &b, &route_diff, &setup.cities, setup.roads.as_ref())).unwrap();
+        println!("Overlay written to {}", svg_file);
+    }
+}
+
+fn main_battle(mut args: Vec<String>) {
+    let export_file = take_export_option(&mut args);
+    let seed = take_seed(&mut args);
+    let setup = load_setup(&mut args);
+    let best = calculations::battle_royale(&setup.cities, &setup.distances, &setup.config, seed);
+    print_route_details(&best, &setup);
+    if let Some(export_file) = &export_file {
+        export_path(export_file, &best, &setup);
+    }
+}
+
+// pareto [--from km] [--to km] [--step km] [--generations N] [--csv file] [--svg file] [--seed N] [options]
+fn main_pareto(mut args: Vec<String>) {
+    let from = take_number(&mut args, "--from", pareto::BUDGET_FROM);
+    let to = take_number(&mut args, "--to", pareto::BUDGET_TO);
+    let step = take_number(&mut args, "--step", pareto::BUDGET_STEP);
+    let generations = take_number(&mut args, "--generations", pareto::GENERATION_COUNT);
+    let csv_file = take_option(&mut args, "--csv").unwrap_or("pareto.csv".to_string());
+    let svg_file = take_option(&mut args, "--svg").unwrap_or("pareto.svg".to_string());
+    let seed = take_seed(&mut args);
+    let setup = load_setup(&mut args);
+
+    let front = pareto::pareto_front(&setup.cities, &setup.distances, &setup.config, to, generations, seed);
+    let budgets = pareto::best_per_budget(&front, from, to, step);
+    for (budget, path) in &budgets {
+        match path {
+            Some(path) => println!("{:>5} km: population {:>8}, distance {:>5}, stops {:>3}", budget, path.population, path.distance, path.city_indexes.len()),
+            None => println!("{:>5} km: no route found", budget),
+        }
+    }
+    if let Some((_, Some(best))) = budgets.last() {
+        println!("Best of the best: {}", best);
+    }
+
+    pareto::write_csv(&csv_file, &budgets, &setup.cities).unwrap();
+    println!("Best route per budget written to {}", csv_file);
+    std::fs::write(&svg_file, svg::render_pareto(&front, &budgets)).unwrap();
+    println!("Chart written to {}", svg_file);
+}
+
+// sweep [--from km] [--to km] [--step km] [--iterations N] [--csv file] [--svg file] [--seed N] [options]
+fn main_sweep(mut args: Vec<String>) {
+    let from = take_number(&mut args, "--from", sweep::SWEEP_FROM);
+    let to = take_number(&mut args, "--to", sweep::SWEEP_TO);
+    let step = take_number(&mut args, "--step", sweep::SWEEP_STEP);
+    let iterations = take_number(&mut args, "--iterations", sweep::SWEEP_ITERATIONS);
+    let csv_file = take_option(&mut args, "--csv").unwrap_or("sweep.csv".to_string());
+    let svg_file = take_option(&mut args, "--svg").unwrap_or("sweep.svg".to_string());
+    let seed = take_seed(&mut args);
+    let setup = load_setup(&mut args);
+    if !setup.config.day_limits.is_empty() {
+        println!("sweep varies --max-distance, it cannot be combined with --days");
+        std::process::exit(2);
+    }
+
+    let steps = sweep::sweep(&setup.cities, &setup.distances, &setup.config, &sweep::budgets(from, to, step), iterations, seed);
+    for step in &steps {
+        if !step.added.is_empty() || !step.removed.is_empty() {
+            println!("{:>5} km: added {}{}", step.budget, city_list(&step.added, &setup),
+                if step.removed.is_empty() { String::new() } else { format!(", removed {}", city_list(&step.removed, &setup)) });
+        }
+    }
+    if let Some(per_100_km) = sweep::population_per_100_km(&steps) {
+        println!("Every extra 100 km brings {:+.0} inhabitants on average", per_100_km);
+    }
+    if let Some(best) = steps.iter().rev().find_map(|step| step.path.as_ref()) {
+        println!("Best of the best: {}", best);
+    }
+
+    sweep::write_csv(&csv_file, &steps, &setup.cities).unwrap();
+    println!("Best route per budget written to {}", csv_file);
+    std::fs::write(&svg_file, svg::render_sweep(&steps, &setup.cities)).unwrap();
+    println!("Chart written to {}", svg_file);
+}
+
+// tune [--candidates N] [--rounds N] [--runs N] [--evaluations N] [--out file] [--seed N] [options]
+fn main_tune(mut args: Vec<String>) {
+    let defaults = tuning::Budget::default();
+    let budget = tuning::Budget {
+        candidates: take_number(&mut args, "--candidates", defaults.candidates),
+        rounds: take_number(&mut args, "--rounds", defaults.rounds),
+        seeds: take_number(&mut args, "--runs", defaults.seeds),
+        evaluations: take_number(&mut args, "--evaluations", defaults.evaluations),
+    };
+    let out_file = take_option(&mut args, "--out").unwrap_or("tuned.txt".to_string());
+    let seed = take_seed(&mut args);
+    let setup = load_setup(&mut args);
+
+    let trials = tuning::tune(&setup.cities, &setup.distances, &setup.config, budget, seed);
+    let best = &trials[0];
+    println!("Best configuration: {}", best.params);
+    println!("Mean best score {:.0} ± {:.0} (95% confidence) over {} runs", best.mean(), best.interval(), best.scores.len());
+    if let Some(current) = trials.iter().find(|trial| trial.params == setup.config.ga) {
+        println!("Current configuration: {:.0} ± {:.0}", current.mean(), current.interval());
+    }
+    std::fs::write(&out_file, tuning::file_text(best, budget, seed)).unwrap();
+    println!("Written to {}, start the optimizer with --ga {}", out_file, out_file);
+}
+
+// export <file.geojson|file.gpx|file.kml|file.svg> [options] <route>
+fn main_export(mut args: Vec<String>) {
+    let setup = load_setup(&mut args);
+    if args.len() < 2 || export::Format::from_filename(&args[0]).is_none() {
+        println!("Usage: export <file.geojson|file.gpx|file.kml|file.svg> [options] <route>");
+        std::process::exit(2);
+    }
+    let Some(path) = scored_path(&args[1..].join(" "), &setup) else {
+        println!("Invalid route: {}", args[1..].join(" "));
+        std::process::exit(2);
+    };
+    println!("{}", path);
+    export_path(&args[0], &path, &setup);
+}
+
+async fn main_with_ui(mut args: Vec<String>) {
+    let seed = take_seed(&mut args);
+    let setup = load_setup(&mut args);
+    let Setup { cities, distances, roads, config } = setup;
+
+    let path = calculations::Path {
         city_indexes: vec![0, 1, 2, 3, 4, 5],
         population: 0,
         distance: 0,
@@ -73,11 +812,11 @@ async fn main_with_ui() {
     let last_result_clone = Arc::clone(&last_result);
     let cities_clone = cities.clone();
     thread::spawn(move || {
-        let mut generation: calculations::Generation = std::array::from_fn(|_| {
-            calculations::generate_random_path()
-        });
-        for tens_thousand in 0..calculations::ITERATION_COUNT/1000 {
-            let best = calculations::do_x_iterations(&mut generation, &cities_clone, &distances, 1000);
+        let mut rng = StdRng::seed_from_u64(seed);
+        println!("Seed: {}", seed);
+        let mut generation = calculations::initial_generation(&cities_clone, &distances, &config, &mut rng);
+        for _tens_thousand in 0..calculations::ITERATION_COUNT/1000 {
+            let best = calculations::do_x_iterations(&mut generation, &cities_clone, &distances, &config, 1000, &mut rng);
             println!("{}", best);
             {
                 let mut result = last_result_clone.lock().unwrap();
@@ -87,23 +826,24 @@ async fn main_with_ui() {
     });
 
     // use the main thread to display data
-    let mut rng = rand::thread_rng();  // Create a random number generator
     loop {
         clear_background(WHITE);
 
-        let path = last_result.lock().unwrap();
-        draw_path(&path, &cities);
+        {
+            let path = last_result.lock().unwrap();
+            draw_path(&path, &cities, roads.as_ref());
+        }
 
         thread::sleep(Duration::from_millis(100));
         next_frame().await;
     }
 }
 
-async fn main_interactive() {
-    let cities = calculations::load_cities("gelnica-chopper/cities.csv").unwrap();
-    let distances = calculations::load_distances("gelnica-chopper/city_distance_matrix.csv").unwrap();
+async fn main_interactive(mut args: Vec<String>) {
+    let export_file = take_export_option(&mut args);
+    let setup = Arc::new(load_setup(&mut args));
 
-    let mut path = calculations::Path {
+    let path = calculations::Path {
         city_indexes: vec![0, 1, 2, 3, 4, 5],
         population: 0,
         distance: 0,
@@ -113,7 +853,7 @@ async fn main_interactive() {
 
     // do the calculations in a thread
     let last_result_clone = Arc::clone(&last_result);
-    let cities_clone = cities.clone();
+    let setup_clone = Arc::clone(&setup);
     thread::spawn(move || {
         use std::io::{stdin,stdout,Write};
 
@@ -125,25 +865,15 @@ async fn main_interactive() {
             println!("You typed: {}", ins);
 
             println!("Parsing input...");
-            let numbers: Vec<usize> = ins
-            .trim()  // Remove trailing newline/whitespace
-            .split(',')  // Split by commas
-            .filter_map(|s| s.trim().parse::<usize>().ok())  // Parse each element, skip invalid entries
-            .collect();
-            if numbers.len() == 0 {
+            let Some(path) = scored_path(&ins, &setup_clone) else {
                 println!("Invalid input");
                 continue;
-            }
-
-            println!("Calculating score...");
-            let mut path = calculations::Path {
-                city_indexes: numbers,
-                population: 0,
-                distance: 0,
-                score: 0,
             };
-            calculations::calculate_score(&mut path, &cities_clone, &distances);
             println!("{}", path);
+            print_route_details(&path, &setup_clone);
+            if let Some(export_file) = &export_file {
+                export_path(export_file, &path, &setup_clone);
+            }
 
             let mut result = last_result_clone.lock().unwrap();
             *result = path; // Replace the old result with the new one
@@ -151,21 +881,222 @@ async fn main_interactive() {
     });
 
     // use the main thread to display data
-    let mut rng = rand::thread_rng();  // Create a random number generator
     loop {
         clear_background(WHITE);
 
-        let path = last_result.lock().unwrap();
-        draw_path(&path, &cities);
+        {
+            let path = last_result.lock().unwrap();
+            draw_path(&path, &setup.cities, setup.roads.as_ref());
+        }
 
         thread::sleep(Duration::from_millis(100));
         next_frame().await;
     }
 }
 
-//#[macroquad::main(window_conf)]
+fn main_validate_data(args: &[String]) {
+    let cities_file = args.first().map_or(data::CITIES_FILE, String::as_str);
+    let distances_file = args.get(1).map_or(data::DISTANCES_FILE, String::as_str);
+
+    let errors = data::validate_dataset(cities_file, distances_file);
+    for error in &errors {
+        println!("{}", error);
+    }
+    if errors.is_empty() {
+        println!("No problems found in {} and {}", cities_file, distances_file);
+    } else {
+        println!("Found {} problem(s)", errors.len());
+        std::process::exit(1);
+    }
+}
+
+// the loaded data, or the problem with the file and exit, as validate-data reports it
+fn loaded<T>(result: Result<T, data::DataError>) -> T {
+    result.unwrap_or_else(|error| {
+        println!("{}", error);
+        std::process::exit(1);
+    })
+}
+
+// --detour <factor> for distances estimated from coordinates, geo::DETOUR_FACTOR when not given
+fn take_detour(args: &mut Vec<String>) -> f64 {
+    match take_option(args, "--detour").map(|value| value.parse()) {
+        None => geo::DETOUR_FACTOR,
+        Some(Ok(value)) if value > 0.0 => value,
+        Some(_) => {
+            println!("--detour expects a positive number, e.g. --detour 1.25");
+            std::process::exit(2);
+        }
+    }
+}
+
+// removes "--name value" from args and returns the value
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let position = args.iter().position(|arg| arg == name)?;
+    if position + 1 >= args.len() {
+        println!("{} expects a value", name);
+        std::process::exit(2);
+    }
+    args.remove(position);
+    Some(args.remove(position))
+}
+
+// --seed <number>, a random one when not given
+fn take_seed(args: &mut Vec<String>) -> u64 {
+    match take_option(args, "--seed").map(|value| value.parse()) {
+        None => rand::random(),
+        Some(Ok(seed)) => seed,
+        Some(Err(_)) => {
+            println!("--seed expects a positive number");
+            std::process::exit(2);
+        }
+    }
+}
+
+// --name <number>, the default when not given
+fn take_number<T: std::str::FromStr>(args: &mut Vec<String>, name: &str, default: T) -> T {
+    match take_option(args, name) {
+        None => default,
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            println!("{} expects a number, got '{}'", name, value);
+            std::process::exit(2);
+        }),
+    }
+}
+
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let position = args.iter().position(|arg| arg == name);
+    if let Some(position) = position {
+        args.remove(position);
+    }
+    position.is_some()
+}
+
+// --osm <file> or --nodes <file> --edges <file>
+fn load_roads(args: &mut Vec<String>, cities: &calculations::Cities) -> Option<roads::RoadNetwork> {
+    let osm_file = take_option(args, "--osm");
+    let nodes_file = take_option(args, "--nodes");
+    let edges_file = take_option(args, "--edges");
+    let (graph, roads_file) = match (osm_file, nodes_file, edges_file) {
+        (Some(osm_file), _, _) => (loaded(roads::load_road_osm(&osm_file)), osm_file),
+        (None, Some(nodes_file), Some(edges_file)) => (loaded(roads::load_road_csv(&nodes_file, &edges_file)), edges_file),
+        (None, None, None) => return None,
+        _ => {
+            println!("--nodes and --edges have to be used together");
+            std::process::exit(2);
+        }
+    };
+    println!("Routing {} cities over {} road nodes...", cities.names.len(), graph.coords.len());
+    let routes = loaded(roads::route_cities(&graph, cities, &roads_file));
+    for (city, &node) in routes.city_nodes.iter().enumerate() {
+        let offset = geo::haversine_km(cities.coords[city], graph.coords[node]);
+        if offset > 5.0 {
+            println!("Warning: {} is {:.1} km away from the closest road node", cities.names[city], offset);
+        }
+    }
+    Some(roads::RoadNetwork { graph, routes })
+}
+
+fn main_derive_distances(mut args: Vec<String>) {
+    let detour_factor = take_detour(&mut args);
+    let cities_file = args.first().map_or(data::CITIES_FILE, String::as_str);
+    let output_file = args.get(1).map_or(data::DISTANCES_FILE, String::as_str);
+
+    let cities = loaded(data::load_cities(cities_file));
+    let distances = geo::distances_from_coords(&cities, detour_factor);
+    geo::write_distances(output_file, &cities, &distances).unwrap();
+    println!("Wrote {}x{} distances to {} (detour factor {})", distances.len(), distances.len(), output_file, detour_factor);
+}
+
+fn main_road_distances(mut args: Vec<String>) {
+    let cities_file = take_option(&mut args, "--cities").unwrap_or(data::CITIES_FILE.to_string());
+    let cities = loaded(data::load_cities(&cities_file));
+    let Some(roads) = load_roads(&mut args, &cities) else {
+        println!("road-distances needs --osm <file> or --nodes <file> --edges <file>");
+        std::process::exit(2);
+    };
+    let output_file = args.first().map_or(data::DISTANCES_FILE, String::as_str);
+    geo::write_distances(output_file, &cities, &roads.routes.distances).unwrap();
+    println!("Wrote {}x{} road distances to {}", cities.names.len(), cities.names.len(), output_file);
+}
+
+fn print_usage() {
+    println!("Usage: gelnica-chopper [command]");
+    println!("Commands:");
+    println!("  battle [options]                        run the battle royale (default)");
+    println!("  ui [options]                            run the genetic algorithm with a live map");
+    println!("  interactive [options]                   score routes typed into the console");
+    println!("  pareto [--from km] [--to km] [--step km] [--generations N] [--csv file] [--svg file] [options]");
+    println!("                                          population/distance trade-off, best route per budget (default 800-2000 km by 50)");
+    println!("  sweep [--from km] [--to km] [--step km] [--iterations N] [--csv file] [--svg file] [options]");
+    println!("                                          re-optimize for every distance budget (default 1000-2000 km by 50), warm-started");
+    println!("  tune [--candidates N] [--rounds N] [--runs N] [--evaluations N] [--out file] [options]");
+    println!("                                          successive halving over GA settings, writes the best as a --ga file");
+    println!("  export <file> [options] <route>         write a route as GeoJSON, GPX, KML or an SVG map (by file extension)");
+    println!("  segments <files...> [--min-length N] [--top N] [--blocks file]");
+    println!("                                          ordered segments shared by the routes in the files");
+    println!("  diff <A> <B> [--svg file] [options]     compare two routes (@<archive id>, a results/log file or a list)");
+    println!("  validate-data [cities] [distances]      report every problem in the dataset");
+    println!("  archive import <files...> [options]     store routes from results.txt or run logs, re-scored");
+    println!("  archive list | best [count] | show <id> browse the archive (--archive <file>, default {})", archive::ARCHIVE_FILE);
+    println!("  derive-distances [--detour F] [cities] [output]");
+    println!("                                          estimate the distance matrix from city coordinates");
+    println!("  road-distances [--cities file] roads [output]");
+    println!("                                          shortest road distances between all cities");
+    println!("Roads are given as --osm <file.osm> or --nodes <nodes.csv> --edges <edges.csv>");
+    println!("Options:");
+    println!("  --cities <file> --distances <file>      dataset to use");
+    println!("  --detour <factor>                       without a distance matrix file: great-circle km times the factor (default {})", geo::DETOUR_FACTOR);
+    println!("  <roads>                                 use road distances and draw routes along roads");
+    println!("  --drive-through                         cities on the road between two stops count as visited");
+    println!("  --export <file>                         battle and interactive: export the resulting route");
+    println!("  --seed <number>                         battle, ui, pareto, sweep and tune: seed of the random generator");
+    println!("  --seed-routes <file>                    start from the routes in the file (results.txt lines or index lists)");
+    println!("  --seed-mutations                        fill the first generation with mutated seed routes instead of random ones");
+    println!("  --max-distance <km|none>                km limit of the route (default 1500), none to limit only the time");
+    println!("  --speed <km/h> | --time-matrix <file>   driving time by average speed (default {}) or by a matrix of minutes", timing::DEFAULT_SPEED);
+    println!("  --dwell <minutes>                       time spent in every visited city (default {})", timing::DEFAULT_DWELL);
+    println!("  --max-hours <hours>                     time budget of the route, e.g. 14 or 13:30");
+    println!("  --start <time>                          time of day the route starts (default 8:00)");
+    println!("  --windows <file>                        opening hours per city (name,open,close), arriving later is not allowed");
+    println!("  --start-city <city>                     where the route starts (default Gelnica), it ends there too unless --end-city is given");
+    println!("  --end-city <city|any>                   where the route ends, any for a one-way trip ending wherever it is best");
+    println!("  --value <model>                         what a city is worth: raw (default), log, sqrt, capped[:cap] or custom (value column)");
+    println!("  --region-bonus | --district-bonus <N>   points for every region/district with a visited city (region/district column)");
+    println!("  --min-per-region <N>                    visit at least N cities in every region (region/kraj column)");
+    println!("  --max-per-district <N>                  visit at most N cities in every district (district/okres column)");
+    println!("  --require <cities> | --avoid <cities>   cities that must (not) be in the route, names or indexes separated by commas");
+    println!("  --bad-cities <cities|none>              cities penalized when visited (default: the ones of the reference cities.csv)");
+    println!("  --days <limits>                         multi-day trip, km limit per day (400,350,400 or 3x400), days end in overnight cities");
+    println!("  --ga <file>                             settings of the genetic algorithm, e.g. written by tune (key=value per line)");
+    println!("  --adaptive-mutation                     adapt the mutation chances to the operators that improve routes, logged every {} iterations", adaptive::LOG_INTERVAL);
+    println!("  --diversity                             mutate copies in the elite, crossover children replace only the parent closest to them (deterministic crowding)");
+    println!("  --local-search                          improve the best route of every run by single removals, insertions, relocations and reversals");
+    println!("  --no-cache                              score every route of every iteration again instead of reusing recent scores");
+    println!("  --init <mix>                            how new paths are made, e.g. random=40,nearest=20,greedy=20,sweep=20");
+}
+
 pub fn main() {
-    main_battle();
-    // main_interactive().await;
-    // main_with_ui().await;
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        None => main_battle(Vec::new()),
+        Some("battle") => main_battle(args[2..].to_vec()),
+        Some("ui") => macroquad::Window::from_config(window_conf(), main_with_ui(args[2..].to_vec())),
+        Some("interactive") => macroquad::Window::from_config(window_conf(), main_interactive(args[2..].to_vec())),
+        Some("archive") => main_archive(args[2..].to_vec()),
+        Some("segments") => main_segments(args[2..].to_vec()),
+        Some("diff") => main_diff(args[2..].to_vec()),
+        Some("pareto") => main_pareto(args[2..].to_vec()),
+        Some("sweep") => main_sweep(args[2..].to_vec()),
+        Some("tune") => main_tune(args[2..].to_vec()),
+        Some("export") => main_export(args[2..].to_vec()),
+        Some("validate-data") => main_validate_data(&args[2..]),
+        Some("derive-distances") => main_derive_distances(args[2..].to_vec()),
+        Some("road-distances") => main_road_distances(args[2..].to_vec()),
+        Some(command) => {
+            println!("Unknown command: {}", command);
+            print_usage();
+            std::process::exit(2);
+        }
+    }
 }
@@ -0,0 +1,448 @@
+use crate::calculations::{
+    Cities, Config, Distances, Path, PENALTY_AVOIDED, PENALTY_BADCITY, PENALTY_DISTANCE, PENALTY_REQUIRED, PENALTY_START_END,
+};
+
+// improving moves applied by a single local search before it gives up
+pub const LOCAL_SEARCH_MOVES: usize = 1000;
+
+// a change of a route that can tell what it does to the score before it is made
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Move {
+    Insert { position: usize, city: usize }, // the city goes before the stop at position, at the end when position is the length
+    Remove { position: usize },
+    Relocate { from: usize, to: usize },     // the stop moves to position to of the route without it
+    Reverse { from: usize, to: usize },      // stops from..=to in the opposite order
+}
+
+impl Move {
+    pub fn apply(&self, stops: &mut Vec<usize>) {
+        match *self {
+            Move::Insert { position, city } => stops.insert(position, city),
+            Move::Remove { position } => {
+                stops.remove(position);
+            }
+            Move::Relocate { from, to } => {
+                let city = stops.remove(from);
+                stops.insert(to, city);
+            }
+            Move::Reverse { from, to } => stops[from..=to].reverse(),
+        }
+    }
+
+    fn len_after(&self, len: usize) -> usize {
+        match self {
+            Move::Insert { .. } => len + 1,
+            Move::Remove { .. } => len - 1,
+            Move::Relocate { .. } | Move::Reverse { .. } => len,
+        }
+    }
+
+    // the stop at index once the move is made, without making it
+    fn stop_after(&self, stops: &[usize], index: usize) -> Option<usize> {
+        match *self {
+            Move::Insert { position, city } => match index.cmp(&position) {
+                std::cmp::Ordering::Less => stops.get(index).copied(),
+                std::cmp::Ordering::Equal => Some(city),
+                std::cmp::Ordering::Greater => stops.get(index - 1).copied(),
+            },
+            Move::Remove { position } => stops.get(if index < position { index } else { index + 1 }).copied(),
+            Move::Relocate { from, to } => {
+                let without = |index: usize| stops.get(if index < from { index } else { index + 1 }).copied();
+                match index.cmp(&to) {
+                    std::cmp::Ordering::Less => without(index),
+                    std::cmp::Ordering::Equal => Some(stops[from]),
+                    std::cmp::Ordering::Greater => without(index - 1),
+                }
+            }
+            Move::Reverse { from, to } => stops.get(if (from..=to).contains(&index) { from + to - index } else { index }).copied(),
+        }
+    }
+}
+
+// what a move changes, positive when it grows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Delta {
+    pub distance: i64,
+    pub population: i64,
+    pub score: i64,
+}
+
+// the per-city part of the scoring, prepared once for a run
+// only there when the score is a plain sum over stops and legs, i.e. without time, days, driving through and coverage rules
+pub struct Scoring<'a> {
+    cities: &'a Cities,
+    distances: &'a Distances,
+    config: &'a Config,
+    values: Vec<u32>,
+    bad: Vec<bool>,      // penalized when visited anywhere but at the end
+    avoided: Vec<bool>,
+    required: Vec<bool>,
+    symmetric: bool,     // reversing a segment does not change the legs inside it
+}
+
+impl<'a> Scoring<'a> {
+    pub fn new(cities: &'a Cities, distances: &'a Distances, config: &'a Config) -> Option<Scoring<'a>> {
+        let plain = config.time.is_none() && config.day_limits.is_empty() && config.drive_through.is_none()
+            && config.region_bonus == 0 && config.district_bonus == 0 && config.min_per_region == 0 && config.max_per_district.is_none();
+        if !plain {
+            return None;
+        }
+        let count = cities.names.len();
+        Some(Scoring {
+            cities,
+            distances,
+            config,
+            values: (0..count).map(|city| config.value.city_value(cities, city)).collect(),
+            bad: (0..count).map(|city| config.is_bad_city(city) && !config.required.contains(&city)).collect(),
+            avoided: (0..count).map(|city| config.avoided.contains(&city)).collect(),
+            required: (0..count).map(|city| config.required.contains(&city)).collect(),
+            symmetric: (0..count).all(|a| (0..a).all(|b| distances[a][b] == distances[b][a])),
+        })
+    }
+
+    fn leg(&self, from: Option<usize>, to: Option<usize>) -> i64 {
+        match (from, to) {
+            (Some(from), Some(to)) => i64::from(self.distances[from][to]),
+            _ => 0,
+        }
+    }
+}
+
+// sums over the route that every move updates
+#[derive(Clone, Copy, Debug, Default)]
+struct Parts {
+    distance: u32,
+    population: u32,
+    value: u32,
+    bad: u32,     // visits of bad cities, the last stop included
+    avoided: u32, // visits of avoided cities
+    missing: u32, // required cities not visited
+}
+
+// a route being changed by moves, calculate_score in O(1) per move
+pub struct Evaluation<'s, 'a> {
+    scoring: &'s Scoring<'a>,
+    counts: Vec<u32>, // visits of every city
+    parts: Parts,
+}
+
+impl<'s, 'a> Evaluation<'s, 'a> {
+    pub fn new(scoring: &'s Scoring<'a>, stops: &[usize]) -> Evaluation<'s, 'a> {
+        let mut counts = vec![0; scoring.values.len()];
+        let mut parts = Parts::default();
+        for (order, &city) in stops.iter().enumerate() {
+            if counts[city] == 0 {
+                parts.population += scoring.cities.populations[city];
+                parts.value += scoring.values[city];
+            }
+            counts[city] += 1;
+            parts.bad += u32::from(scoring.bad[city]);
+            parts.avoided += u32::from(scoring.avoided[city]);
+            if order > 0 {
+                parts.distance += scoring.distances[stops[order - 1]][city];
+            }
+        }
+        parts.missing = scoring.config.required.iter().filter(|&&city| counts[city] == 0).count() as u32;
+        Evaluation { scoring, counts, parts }
+    }
+
+    // the same as calculate_score would give
+    fn score(&self, parts: &Parts, first: Option<usize>, last: Option<usize>) -> u32 {
+        let config = self.scoring.config;
+        let extra_distance = config.max_distance.map_or(0, |max_distance| parts.distance.saturating_sub(max_distance));
+        let mut penalty = extra_distance * PENALTY_DISTANCE;
+        if first != Some(config.start) {
+            penalty += PENALTY_START_END;
+        }
+        if config.end.is_some_and(|end| last != Some(end)) {
+            penalty += PENALTY_START_END;
+        }
+        let bad_at_end = last.map_or(0, |last| u32::from(self.scoring.bad[last]));
+        penalty += (parts.bad - bad_at_end) * PENALTY_BADCITY + parts.missing * PENALTY_REQUIRED + parts.avoided * PENALTY_AVOIDED;
+        parts.value.saturating_sub(penalty)
+    }
+
+    fn visit(&self, parts: &mut Parts, city: usize) {
+        let scoring = self.scoring;
+        if self.counts[city] == 0 {
+            parts.population += scoring.cities.populations[city];
+            parts.value += scoring.values[city];
+            parts.missing -= u32::from(scoring.required[city]);
+        }
+        parts.bad += u32::from(scoring.bad[city]);
+        parts.avoided += u32::from(scoring.avoided[city]);
+    }
+
+    fn leave(&self, parts: &mut Parts, city: usize) {
+        let scoring = self.scoring;
+        if self.counts[city] == 1 {
+            parts.population -= scoring.cities.populations[city];
+            parts.value -= scoring.values[city];
+            parts.missing += u32::from(scoring.required[city]);
+        }
+        parts.bad -= u32::from(scoring.bad[city]);
+        parts.avoided -= u32::from(scoring.avoided[city]);
+    }
+
+    fn parts_after(&self, mv: &Move, stops: &[usize]) -> Parts {
+        let scoring = self.scoring;
+        let at = |index: usize| stops.get(index).copied();
+        let before = |index: usize| index.checked_sub(1).and_then(at);
+        let mut parts = self.parts;
+        let change = match *mv {
+            Move::Insert { position, city } => {
+                self.visit(&mut parts, city);
+                scoring.leg(before(position), Some(city)) + scoring.leg(Some(city), at(position)) - scoring.leg(before(position), at(position))
+            }
+            Move::Remove { position } => {
+                self.leave(&mut parts, stops[position]);
+                scoring.leg(before(position), at(position + 1)) - scoring.leg(before(position), at(position)) - scoring.leg(at(position), at(position + 1))
+            }
+            Move::Relocate { from, to } => {
+                let city = Some(stops[from]);
+                let removed = scoring.leg(before(from), at(from + 1)) - scoring.leg(before(from), city) - scoring.leg(city, at(from + 1));
+                let without = |index: usize| at(if index < from { index } else { index + 1 });
+                let previous = to.checked_sub(1).and_then(without);
+                let next = if to < stops.len() - 1 { without(to) } else { None };
+                removed + scoring.leg(previous, city) + scoring.leg(city, next) - scoring.leg(previous, next)
+            }
+            Move::Reverse { from, to } => {
+                let inside = if scoring.symmetric {
+                    0
+                } else {
+                    (from..to).map(|i| scoring.leg(at(i + 1), at(i)) - scoring.leg(at(i), at(i + 1))).sum()
+                };
+                inside + scoring.leg(before(from), at(to)) + scoring.leg(at(from), at(to + 1))
+                    - scoring.leg(before(from), at(from)) - scoring.leg(at(to), at(to + 1))
+            }
+        };
+        parts.distance = (i64::from(parts.distance) + change) as u32;
+        parts
+    }
+
+    fn ends_after(mv: &Move, stops: &[usize]) -> (Option<usize>, Option<usize>) {
+        let len = mv.len_after(stops.len());
+        (mv.stop_after(stops, 0), len.checked_sub(1).and_then(|last| mv.stop_after(stops, last)))
+    }
+
+    pub fn delta(&self, mv: &Move, stops: &[usize]) -> Delta {
+        let parts = self.parts_after(mv, stops);
+        let (first, last) = Self::ends_after(mv, stops);
+        let score = self.score(&parts, first, last);
+        let current = self.score(&self.parts, stops.first().copied(), stops.last().copied());
+        Delta {
+            distance: i64::from(parts.distance) - i64::from(self.parts.distance),
+            population: i64::from(parts.population) - i64::from(self.parts.population),
+            score: i64::from(score) - i64::from(current),
+        }
+    }
+
+    // makes the move and keeps the sums up to date
+    pub fn apply(&mut self, mv: &Move, stops: &mut Vec<usize>) {
+        self.parts = self.parts_after(mv, stops);
+        match *mv {
+            Move::Insert { city, .. } => self.counts[city] += 1,
+            Move::Remove { position } => self.counts[stops[position]] -= 1,
+            Move::Relocate { .. } | Move::Reverse { .. } => {}
+        }
+        mv.apply(stops);
+    }
+
+    // distance, population and score of the path, its stops must be the ones the moves were applied to
+    pub fn fill(&self, path: &mut Path) {
+        path.distance = self.parts.distance;
+        path.population = self.parts.population;
+        path.score = self.score(&self.parts, path.city_indexes.first().copied(), path.city_indexes.last().copied());
+    }
+}
+
+// every move that keeps the start, and the end unless the route may end anywhere
+fn candidate_moves(stops: &[usize], evaluation: &Evaluation) -> Vec<Move> {
+    let scoring = evaluation.scoring;
+    let len = stops.len();
+    // positions that may change
+    let last = if scoring.config.end.is_some() { len.saturating_sub(1) } else { len };
+    let mut moves = Vec::new();
+    if len > 2 {
+        moves.extend((1..last).map(|position| Move::Remove { position }));
+    }
+    for from in 1..last {
+        // the route without the stop has len - 1 stops, its end stays last
+        let to_last = if scoring.config.end.is_some() { len - 2 } else { len - 1 };
+        moves.extend((1..=to_last).filter(|&to| to != from).map(|to| Move::Relocate { from, to }));
+        moves.extend((from + 1..last).map(|to| Move::Reverse { from, to }));
+    }
+    for city in (0..scoring.values.len()).filter(|&city| evaluation.counts[city] == 0 && !scoring.avoided[city]) {
+        moves.extend((1..=last).map(|position| Move::Insert { position, city }));
+    }
+    moves
+}
+
+// first-improvement hill climbing over removals, insertions of unvisited cities, relocations and segment reversals
+// returns the number of moves made
+pub fn local_search(path: &mut Path, scoring: &Scoring) -> usize {
+    let mut evaluation = Evaluation::new(scoring, &path.city_indexes);
+    let mut made = 0;
+    while made < LOCAL_SEARCH_MOVES && !path.city_indexes.is_empty() {
+        let improving = candidate_moves(&path.city_indexes, &evaluation).into_iter()
+            .find(|mv| evaluation.delta(mv, &path.city_indexes).score > 0);
+        let Some(mv) = improving else {
+            break;
+        };
+        evaluation.apply(&mv, &mut path.city_indexes);
+        made += 1;
+    }
+    evaluation.fill(path);
+    made
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculations::{self, Grouping};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // more cities than the default start and bad cities need, with random populations and asymmetric distances
+    const CITIES: usize = 150;
+
+    fn dataset(rng: &mut StdRng) -> (Cities, Distances) {
+        let cities = Cities {
+            names: (0..CITIES).map(|city| format!("city{}", city)).collect(),
+            populations: (0..CITIES).map(|_| rng.gen_range(100..5000)).collect(),
+            coords: vec![(0.0, 0.0); CITIES],
+            overnight: vec![true; CITIES],
+            values: vec![None; CITIES],
+            regions: Grouping::default(),
+            districts: Grouping::default(),
+        };
+        let distances = (0..CITIES)
+            .map(|from| (0..CITIES).map(|to| if from == to { 0 } else { rng.gen_range(1..80) }).collect())
+            .collect();
+        (cities, distances)
+    }
+
+    // a route that ends anywhere, within a budget, with required and avoided cities
+    fn open_config() -> Config {
+        Config { end: None, max_distance: Some(300), required: vec![5, 100, 40], avoided: vec![7, 12], ..Config::default() }
+    }
+
+    fn scored(stops: &[usize], cities: &Cities, distances: &Distances, config: &Config) -> Path {
+        let mut path = Path { city_indexes: stops.to_vec(), population: 0, distance: 0, score: 0 };
+        calculations::calculate_score(&mut path, cities, distances, config);
+        path
+    }
+
+    fn random_move(stops: &[usize], rng: &mut StdRng) -> Move {
+        let len = stops.len();
+        match rng.gen_range(0..4) {
+            0 => Move::Insert { position: rng.gen_range(0..=len), city: rng.gen_range(0..CITIES) },
+            1 if len > 1 => Move::Remove { position: rng.gen_range(0..len) },
+            2 if len > 1 => {
+                let from = rng.gen_range(0..len);
+                let to = (from + rng.gen_range(1..len)) % len;
+                Move::Relocate { from, to }
+            }
+            _ => {
+                let from = rng.gen_range(0..len);
+                Move::Reverse { from, to: rng.gen_range(from..len) }
+            }
+        }
+    }
+
+    fn assert_deltas_match_scores(config: &Config, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (cities, distances) = dataset(&mut rng);
+        let scoring = Scoring::new(&cities, &distances, config).unwrap();
+        for _ in 0..100 {
+            let mut stops: Vec<usize> = (0..rng.gen_range(2..30)).map(|_| rng.gen_range(0..CITIES)).collect();
+            let mut evaluation = Evaluation::new(&scoring, &stops);
+            for _ in 0..30 {
+                let mv = random_move(&stops, &mut rng);
+                let before = scored(&stops, &cities, &distances, config);
+                let delta = evaluation.delta(&mv, &stops);
+                evaluation.apply(&mv, &mut stops);
+                let after = scored(&stops, &cities, &distances, config);
+                assert_eq!(delta.score, i64::from(after.score) - i64::from(before.score), "{:?}", mv);
+                assert_eq!(delta.distance, i64::from(after.distance) - i64::from(before.distance), "{:?}", mv);
+                assert_eq!(delta.population, i64::from(after.population) - i64::from(before.population), "{:?}", mv);
+                let mut filled = Path { city_indexes: stops.clone(), population: 0, distance: 0, score: 0 };
+                evaluation.fill(&mut filled);
+                assert_eq!((filled.score, filled.distance, filled.population), (after.score, after.distance, after.population), "{:?}", mv);
+            }
+        }
+    }
+
+    #[test]
+    fn deltas_match_calculate_score() {
+        assert_deltas_match_scores(&Config::default(), 1);
+    }
+
+    #[test]
+    fn deltas_match_calculate_score_of_an_open_route() {
+        assert_deltas_match_scores(&open_config(), 2);
+    }
+
+    #[test]
+    fn local_search_keeps_the_start_and_the_end() {
+        // within a budget, otherwise nearly every insertion improves and the search runs for LOCAL_SEARCH_MOVES
+        let closed = Config { max_distance: Some(300), ..Config::default() };
+        for (config, seed) in [(closed, 3), (open_config(), 4)] {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (cities, distances) = dataset(&mut rng);
+            let scoring = Scoring::new(&cities, &distances, &config).unwrap();
+            for _ in 0..5 {
+                // with the required cities, a single move cannot make up for a missing one
+                let mut stops: Vec<usize> = (0..rng.gen_range(0..5)).map(|_| rng.gen_range(0..CITIES)).collect();
+                stops.extend(&config.required);
+                stops.insert(0, config.start);
+                stops.push(config.end.unwrap_or(rng.gen_range(0..CITIES)));
+                let mut path = scored(&stops, &cities, &distances, &config);
+                let before = path.score;
+                assert!(local_search(&mut path, &scoring) > 0);
+                assert_eq!(path.city_indexes.first(), stops.first());
+                if config.end.is_some() {
+                    assert_eq!(path.city_indexes.last(), stops.last());
+                }
+                assert!(path.score >= before);
+                assert_eq!(path.score, scored(&path.city_indexes, &cities, &distances, &config).score);
+            }
+        }
+    }
+
+    // how do_x_iterations scores a mutated offspring: through an evaluation of the parent or with calculate_score afterwards
+    // run with cargo test --release -- --ignored --nocapture mutation_scoring_speed
+    #[test]
+    #[ignore]
+    fn mutation_scoring_speed() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let (cities, distances) = dataset(&mut rng);
+        let config = Config::default();
+        let scoring = Scoring::new(&cities, &distances, &config).unwrap();
+        let parents: Vec<Path> = (0..100).map(|_| {
+            let mut stops: Vec<usize> = (0..35).map(|_| rng.gen_range(0..CITIES)).collect();
+            stops.insert(0, config.start);
+            stops.push(config.start);
+            scored(&stops, &cities, &distances, &config)
+        }).collect();
+        let chances = config.ga.chances();
+        let time = |score: &dyn Fn(&Path, &mut StdRng) -> Path| {
+            let mut rng = StdRng::seed_from_u64(6);
+            let start = std::time::Instant::now();
+            let scores: Vec<u32> = (0..2000).flat_map(|_| parents.iter().map(|parent| score(parent, &mut rng).score).collect::<Vec<_>>()).collect();
+            (start.elapsed(), scores)
+        };
+        let (mutation, _) = time(&|parent, rng| calculations::mutate_with_chances(parent, &cities, &config, chances, None, rng).0);
+        let (evaluated, by_moves) = time(&|parent, rng| {
+            let mut evaluation = Evaluation::new(&scoring, &parent.city_indexes);
+            calculations::mutate_with_chances(parent, &cities, &config, chances, Some(&mut evaluation), rng).0
+        });
+        let (rescored, by_calculate_score) = time(&|parent, rng| {
+            let mut child = calculations::mutate_with_chances(parent, &cities, &config, chances, None, rng).0;
+            calculations::calculate_score(&mut child, &cities, &distances, &config);
+            child
+        });
+        assert_eq!(by_moves, by_calculate_score);
+        println!("mutation alone {} ms, scored by moves {} ms, scored by calculate_score {} ms", mutation.as_millis(), evaluated.as_millis(), rescored.as_millis());
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+
+use crate::calculations::{
+    BOUNDARY_ELITE, BOUNDARY_MUTATE, BOUNDARY_RANDOM, GENERATION_SIZE, MUTATION_CHANCE_ADDONE, MUTATION_CHANCE_FIRSTCITY,
+    MUTATION_CHANCE_LASTCITY, MUTATION_CHANCE_REMOVEONE, MUTATION_CHANCE_REORDERONE,
+};
+
+// how crossover builds the offspring
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Crossover {
+    #[default]
+    Mixed,    // either of the two below, picked at random for every offspring
+    OnePoint, // head of one parent, tail of the other
+    Slices,   // alternating slices of both parents
+}
+
+pub const CROSSOVERS: [Crossover; 3] = [Crossover::Mixed, Crossover::OnePoint, Crossover::Slices];
+
+impl fmt::Display for Crossover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Crossover::Mixed => write!(f, "mixed"),
+            Crossover::OnePoint => write!(f, "onepoint"),
+            Crossover::Slices => write!(f, "slices"),
+        }
+    }
+}
+
+// settings of the genetic algorithm, the default is given by the constants in calculations
+// boundaries are percentages of the generation: elite up to elite, mutated up to mutate, random up to random, crossovers the rest
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GaParams {
+    pub generation_size: usize,
+    pub boundary_elite: usize,
+    pub boundary_mutate: usize,
+    pub boundary_random: usize,
+    pub chance_firstcity: u8,
+    pub chance_lastcity: u8,
+    pub chance_addone: u8,
+    pub chance_removeone: u8,
+    pub chance_reorderone: u8,
+    pub crossover: Crossover,
+}
+
+impl Default for GaParams {
+    fn default() -> Self {
+        GaParams {
+            generation_size: GENERATION_SIZE,
+            boundary_elite: BOUNDARY_ELITE,
+            boundary_mutate: BOUNDARY_MUTATE,
+            boundary_random: BOUNDARY_RANDOM,
+            chance_firstcity: MUTATION_CHANCE_FIRSTCITY,
+            chance_lastcity: MUTATION_CHANCE_LASTCITY,
+            chance_addone: MUTATION_CHANCE_ADDONE,
+            chance_removeone: MUTATION_CHANCE_REMOVEONE,
+            chance_reorderone: MUTATION_CHANCE_REORDERONE,
+            crossover: Crossover::Mixed,
+        }
+    }
+}
+
+// key=value pairs separated by commas, the same as a params file with one pair per line
+impl fmt::Display for GaParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = self.pairs().iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+impl GaParams {
+    fn pairs(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("generation_size", self.generation_size.to_string()),
+            ("boundary_elite", self.boundary_elite.to_string()),
+            ("boundary_mutate", self.boundary_mutate.to_string()),
+            ("boundary_random", self.boundary_random.to_string()),
+            ("chance_firstcity", self.chance_firstcity.to_string()),
+            ("chance_lastcity", self.chance_lastcity.to_string()),
+            ("chance_addone", self.chance_addone.to_string()),
+            ("chance_removeone", self.chance_removeone.to_string()),
+            ("chance_reorderone", self.chance_reorderone.to_string()),
+            ("crossover", self.crossover.to_string()),
+        ]
+    }
+
+    // key=value pairs separated by commas or lines, lines starting with # are comments
+    // keys that are not given keep their default
+    pub fn parse(text: &str) -> Result<GaParams, String> {
+        let mut params = GaParams::default();
+        for pair in text.lines().filter(|line| !line.trim_start().starts_with('#')).flat_map(|line| line.split(',')) {
+            let pair = pair.trim();
+            if pair.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = pair.split_once('=') else {
+                return Err(format!("'{}' is not a key=value pair", pair));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let invalid = || format!("'{}' is not a valid value of {}", value, key);
+            match key {
+                "generation_size" => params.generation_size = value.parse().map_err(|_| invalid())?,
+                "boundary_elite" => params.boundary_elite = value.parse().map_err(|_| invalid())?,
+                "boundary_mutate" => params.boundary_mutate = value.parse().map_err(|_| invalid())?,
+                "boundary_random" => params.boundary_random = value.parse().map_err(|_| invalid())?,
+                "chance_firstcity" => params.chance_firstcity = value.parse().map_err(|_| invalid())?,
+                "chance_lastcity" => params.chance_lastcity = value.parse().map_err(|_| invalid())?,
+                "chance_addone" => params.chance_addone = value.parse().map_err(|_| invalid())?,
+                "chance_removeone" => params.chance_removeone = value.parse().map_err(|_| invalid())?,
+                "chance_reorderone" => params.chance_reorderone = value.parse().map_err(|_| invalid())?,
+                "crossover" => {
+                    params.crossover = CROSSOVERS.into_iter().find(|crossover| crossover.to_string() == value).ok_or_else(invalid)?;
+                }
+                _ => return Err(format!("unknown parameter '{}'", key)),
+            }
+        }
+        params.validate()?;
+        Ok(params)
+    }
+
+    // the slices of the generation must be in order and leave parents for the crossovers
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.boundary_elite <= self.boundary_mutate && self.boundary_mutate <= self.boundary_random && self.boundary_random <= 100) {
+            return Err("boundaries must grow: boundary_elite <= boundary_mutate <= boundary_random <= 100".to_string());
+        }
+        if self.generation_size * self.boundary_elite / 100 == 0 {
+            return Err("the elite of the generation is empty, raise generation_size or boundary_elite".to_string());
+        }
+        if self.chances().iter().any(|&chance| chance > 100) {
+            return Err("mutation chances are percentages, at most 100".to_string());
+        }
+        Ok(())
+    }
+
+    // mutation chances in the order of adaptive::OPERATORS
+    pub fn chances(&self) -> [u8; 5] {
+        [self.chance_firstcity, self.chance_lastcity, self.chance_addone, self.chance_removeone, self.chance_reorderone]
+    }
+
+    // one pair per line, readable by parse
+    pub fn file_text(&self) -> String {
+        self.pairs().iter().map(|(key, value)| format!("{}={}\n", key, value)).collect()
+    }
+}
@@ -0,0 +1,245 @@
+use std::error::Error;
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use csv::Writer;
+
+use crate::calculations::{self, Cities, Config, Distances, Path};
+use crate::construct;
+use crate::coverage;
+
+pub const POPULATION_SIZE: usize = 200;
+pub const GENERATION_COUNT: usize = 3000;
+const CROSSOVER_CHANCE: f64 = 0.5;
+
+// budgets reported by default, in km
+pub const BUDGET_FROM: u32 = 800;
+pub const BUDGET_TO: u32 = 2000;
+pub const BUDGET_STEP: u32 = 50;
+
+// path with the bookkeeping of NSGA-II
+#[derive(Clone)]
+struct Individual {
+    path: Path,
+    violation: u32, // 0 for a valid route not longer than the longest budget and within the time model
+    rank: usize,    // index of the non-dominated front, 0 is the best
+    crowding: f64,  // distance to the neighbours on the same front, bigger is more unique
+}
+
+fn evaluate(mut path: Path, cities: &Cities, distances: &Distances, config: &Config, max_distance: u32) -> Individual {
+    calculations::calculate_score(&mut path, cities, distances, config);
+    let visited = calculations::visited_cities(&path, config);
+    let violation = calculations::route_penalty(&path, config) + coverage::violations(&visited, cities, config)
+        + path.distance.saturating_sub(max_distance)
+        + config.time.as_ref().map_or(0, |time| time.violation(&path.city_indexes, distances));
+    Individual { path, violation, rank: 0, crowding: 0.0 }
+}
+
+// more population for less distance, a valid route always dominates an invalid one
+fn dominates(a: &Individual, b: &Individual) -> bool {
+    if a.violation != b.violation {
+        return a.violation < b.violation;
+    }
+    a.path.population >= b.path.population && a.path.distance <= b.path.distance
+        && (a.path.population > b.path.population || a.path.distance < b.path.distance)
+}
+
+// splits the individuals into non-dominated fronts and sets their rank
+fn non_dominated_sort(individuals: &mut [Individual]) -> Vec<Vec<usize>> {
+    let count = individuals.len();
+    let mut dominated_by: Vec<usize> = vec![0; count];
+    let mut dominating: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for i in 0..count {
+        for j in (i + 1)..count {
+            if dominates(&individuals[i], &individuals[j]) {
+                dominating[i].push(j);
+                dominated_by[j] += 1;
+            } else if dominates(&individuals[j], &individuals[i]) {
+                dominating[j].push(i);
+                dominated_by[i] += 1;
+            }
+        }
+    }
+
+    let mut fronts = Vec::new();
+    let mut front: Vec<usize> = (0..count).filter(|&i| dominated_by[i] == 0).collect();
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for &i in &front {
+            individuals[i].rank = fronts.len();
+            for &j in &dominating[i] {
+                dominated_by[j] -= 1;
+                if dominated_by[j] == 0 {
+                    next.push(j);
+                }
+            }
+        }
+        fronts.push(front);
+        front = next;
+    }
+    fronts
+}
+
+// crowding distance over both objectives, the ends of the front are always kept
+fn assign_crowding(individuals: &mut [Individual], front: &[usize]) {
+    for &i in front {
+        individuals[i].crowding = 0.0;
+    }
+    let objectives: [fn(&Path) -> f64; 2] = [|path| f64::from(path.population), |path| f64::from(path.distance)];
+    for objective in objectives {
+        let mut sorted = front.to_vec();
+        sorted.sort_by(|&a, &b| objective(&individuals[a].path).total_cmp(&objective(&individuals[b].path)));
+        let (first, last) = (sorted[0], sorted[sorted.len() - 1]);
+        individuals[first].crowding = f64::INFINITY;
+        individuals[last].crowding = f64::INFINITY;
+        let range = objective(&individuals[last].path) - objective(&individuals[first].path);
+        if range <= 0.0 {
+            continue;
+        }
+        for k in 1..sorted.len().saturating_sub(1) {
+            let gap = objective(&individuals[sorted[k + 1]].path) - objective(&individuals[sorted[k - 1]].path);
+            individuals[sorted[k]].crowding += gap / range;
+        }
+    }
+}
+
+// binary tournament, lower rank wins, then the less crowded one
+fn tournament<'a>(individuals: &'a [Individual], rng: &mut StdRng) -> &'a Individual {
+    let a = &individuals[rng.gen_range(0..individuals.len())];
+    let b = &individuals[rng.gen_range(0..individuals.len())];
+    if a.rank != b.rank {
+        if a.rank < b.rank { a } else { b }
+    } else if a.crowding >= b.crowding {
+        a
+    } else {
+        b
+    }
+}
+
+// keeps the best POPULATION_SIZE individuals, whole fronts first and the least crowded part of the last one
+fn select_survivors(mut individuals: Vec<Individual>) -> Vec<Individual> {
+    let fronts = non_dominated_sort(&mut individuals);
+    let mut survivors = Vec::with_capacity(POPULATION_SIZE);
+    for front in fronts {
+        assign_crowding(&mut individuals, &front);
+        let mut front = front;
+        if survivors.len() + front.len() > POPULATION_SIZE {
+            front.sort_by(|&a, &b| individuals[b].crowding.total_cmp(&individuals[a].crowding));
+            front.truncate(POPULATION_SIZE - survivors.len());
+        }
+        survivors.extend(front.iter().map(|&i| individuals[i].clone()));
+        if survivors.len() == POPULATION_SIZE {
+            break;
+        }
+    }
+    survivors
+}
+
+// valid routes none of which is longer and poorer than another, ordered by distance
+// adding a route drops the ones it beats, so the front only gets better
+fn add_to_front(front: &mut Vec<Path>, path: &Path) {
+    if front.iter().any(|other| other.distance <= path.distance && other.population >= path.population) {
+        return;
+    }
+    front.retain(|other| !(other.distance >= path.distance && other.population <= path.population));
+    let position = front.partition_point(|other| other.distance < path.distance);
+    front.insert(position, path.clone());
+}
+
+// seed routes, then constructed tours with budgets spread up to max_distance, so the whole front is covered from the start
+fn initial_population(cities: &Cities, distances: &Distances, config: &Config, max_distance: u32, rng: &mut StdRng) -> Vec<Path> {
+    let mut paths: Vec<Path> = config.seed_routes.iter().take(POPULATION_SIZE).map(|city_indexes| Path {
+        city_indexes: city_indexes.clone(),
+        population: 0,
+        distance: 0,
+        score: 0,
+    }).collect();
+    while paths.len() < POPULATION_SIZE {
+        let rules = construct::Rules {
+            km: Some(max_distance * (paths.len() as u32 + 1) / POPULATION_SIZE as u32),
+            ..config.construct_rules()
+        };
+        let city_indexes = match paths.len() % 3 {
+            0 => construct::randomized_greedy(cities, distances, rules, rng),
+            1 => construct::nearest_neighbour(cities, distances, rules, rng),
+            _ => construct::sweep(cities, distances, rules, rng),
+        };
+        paths.push(Path { city_indexes, population: 0, distance: 0, score: 0 });
+    }
+    for path in paths.iter_mut() {
+        calculations::repair(path, cities, distances, config);
+    }
+    paths
+}
+
+// NSGA-II over population (max) and distance (min), returns every non-dominated valid route seen
+// routes longer than max_distance count as invalid, so do routes over the time budget or late for a city
+pub fn pareto_front(cities: &Cities, distances: &Distances, config: &Config, max_distance: u32, generations: usize, seed: u64) -> Vec<Path> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    println!("Seed: {}", seed);
+    println!("Config: {}", config);
+    println!("Searching the Pareto front up to {} km..", max_distance);
+    let mut front = Vec::new();
+    let mut individuals: Vec<Individual> = initial_population(cities, distances, config, max_distance, &mut rng)
+        .into_iter()
+        .map(|path| evaluate(path, cities, distances, config, max_distance))
+        .collect();
+    individuals = select_survivors(individuals);
+
+    for generation in 1..=generations {
+        let mut offspring = Vec::with_capacity(POPULATION_SIZE);
+        while offspring.len() < POPULATION_SIZE {
+            let parent1 = tournament(&individuals, &mut rng);
+            let child = if rng.gen_bool(CROSSOVER_CHANCE) {
+                let parent2 = tournament(&individuals, &mut rng);
+                calculations::crossover(&parent1.path, &parent2.path, config.ga.crossover, &mut rng)
+            } else {
+                parent1.path.clone()
+            };
+            let mut child = calculations::mutate(&child, cities, config, &mut rng);
+            calculations::repair(&mut child, cities, distances, config);
+            if child.city_indexes.len() >= 2 {
+                offspring.push(evaluate(child, cities, distances, config, max_distance));
+            }
+        }
+        for individual in offspring.iter().filter(|individual| individual.violation == 0) {
+            add_to_front(&mut front, &individual.path);
+        }
+        individuals.extend(offspring);
+        individuals = select_survivors(individuals);
+
+        if generation % 500 == 0 {
+            println!("Generation {}: {} routes on the front, up to {} inhabitants", generation, front.len(),
+                front.last().map_or(0, |path| path.population));
+        }
+    }
+    front
+}
+
+// the best route of the front not longer than each budget
+pub fn best_per_budget(front: &[Path], from: u32, to: u32, step: u32) -> Vec<(u32, Option<&Path>)> {
+    (from..=to).step_by(step.max(1) as usize)
+        .map(|budget| (budget, front.iter().rev().find(|path| path.distance <= budget)))
+        .collect()
+}
+
+// one line per budget: budget, population, distance, stops, city indexes and names
+pub fn write_csv(filename: &str, budgets: &[(u32, Option<&Path>)], cities: &Cities) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_path(filename)?;
+    wtr.write_record(["budget", "population", "distance", "stops", "cities", "names"])?;
+    for (budget, path) in budgets {
+        let record = match path {
+            Some(path) => {
+                let indexes: Vec<String> = path.city_indexes.iter().map(|city| city.to_string()).collect();
+                let names: Vec<&str> = path.city_indexes.iter().map(|&city| cities.names[city].as_str()).collect();
+                vec![budget.to_string(), path.population.to_string(), path.distance.to_string(),
+                    path.city_indexes.len().to_string(), indexes.join(", "), names.join(" - ")]
+            }
+            None => vec![budget.to_string(), String::new(), String::new(), String::new(), String::new(), String::new()],
+        };
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
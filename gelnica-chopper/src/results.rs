@@ -0,0 +1,124 @@
+use crate::calculations;
+
+// route found in results.txt or in the output of a run
+#[derive(Clone, Debug)]
+pub struct ParsedRoute {
+    pub line: usize, // 1-based line where the route starts
+    pub city_indexes: Vec<usize>,
+    pub seed: Option<u64>,      // last "Seed: ..." printed before the route
+    pub config: Option<String>, // last "Config: ..." printed before the route
+}
+
+fn is_bare_list(line: &str) -> bool {
+    line.contains(',') && line.chars().all(|c| c.is_ascii_digit() || c == ',' || c.is_whitespace())
+}
+
+// pulls every route out of the text, understood are
+// - "Score: ..., Cities: [...]" lines, also prefixed by "CANDIDATE 3:" or "Best of the best:"
+// - bare comma separated lists "91, 2, 59, 91"
+// - arrays "[91, 2, ..." wrapped over several lines until the closing "]"
+// "Common sequences" lines list segments, not routes, and are skipped
+pub fn parse_routes(text: &str) -> Vec<ParsedRoute> {
+    let mut routes = Vec::new();
+    let mut seed = None;
+    let mut config = None;
+    // text of a wrapped array collected so far, with the line where it started
+    let mut pending: Option<(usize, String)> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if let Some((start, collected)) = pending.as_mut() {
+            collected.push_str(line);
+            collected.push(' ');
+            if line.contains(']') {
+                let (start, collected) = (*start, collected.clone());
+                pending = None;
+                let city_indexes = calculations::parse_city_indexes(&collected);
+                if !city_indexes.is_empty() {
+                    routes.push(ParsedRoute { line: start, city_indexes, seed, config: config.clone() });
+                }
+            }
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("Seed:") {
+            seed = value.trim().parse().ok();
+        } else if let Some(value) = line.strip_prefix("Config:") {
+            config = Some(value.trim().to_string());
+        } else if line.starts_with("Common sequences") {
+            continue;
+        } else if let Some(position) = line.find("Cities: [").or_else(|| if line.starts_with('[') { Some(0) } else { None }) {
+            if line[position..].contains(']') {
+                let city_indexes = calculations::parse_city_indexes(line);
+                if !city_indexes.is_empty() {
+                    routes.push(ParsedRoute { line: index + 1, city_indexes, seed, config: config.clone() });
+                }
+            } else {
+                pending = Some((index + 1, format!("{} ", &line[position..])));
+            }
+        } else if is_bare_list(line) {
+            let city_indexes = calculations::parse_city_indexes(line);
+            if !city_indexes.is_empty() {
+                routes.push(ParsedRoute { line: index + 1, city_indexes, seed, config: config.clone() });
+            }
+        }
+    }
+    routes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexes(routes: &[ParsedRoute]) -> Vec<Vec<usize>> {
+        routes.iter().map(|route| route.city_indexes.clone()).collect()
+    }
+
+    #[test]
+    fn results_file_lines() {
+        let text = "Common sequences (Array1 & Array2): [[29, 108, 98], [101, 20, 59]]\n\
+                    91, 74, 99, 91\n\
+                    \n\
+                    Score: 2703416, Population: 2703416, Distance: 1494, Cities: [91, 15, 9, 91]\n\
+                    [91, 7, 100,\n\
+                    115, 4,\n\
+                    46, 91]\n";
+        let routes = parse_routes(text);
+        assert_eq!(indexes(&routes), vec![vec![91, 74, 99, 91], vec![91, 15, 9, 91], vec![91, 7, 100, 115, 4, 46, 91]]);
+        assert_eq!(routes.iter().map(|route| route.line).collect::<Vec<_>>(), vec![2, 4, 5]);
+        assert!(routes.iter().all(|route| route.seed.is_none() && route.config.is_none()));
+    }
+
+    #[test]
+    fn run_log_lines() {
+        let text = "Seed: 42\n\
+                    Config: start=91 end=91\n\
+                    Starting battle..\n\
+                    Score: 10, Population: 10, Distance: 5, Cities: [91, 1, 91]\n\
+                    CANDIDATE 3: Score: 20, Population: 20, Distance: 6, Cities: [91, 2, 91]\n\
+                    Seed: 7\n\
+                    Best of the best: Score: 30, Population: 30, Distance: 7, Cities: [91, 3, 91]\n";
+        let routes = parse_routes(text);
+        assert_eq!(indexes(&routes), vec![vec![91, 1, 91], vec![91, 2, 91], vec![91, 3, 91]]);
+        assert_eq!(routes.iter().map(|route| route.seed).collect::<Vec<_>>(), vec![Some(42), Some(42), Some(7)]);
+        assert!(routes.iter().all(|route| route.config.as_deref() == Some("start=91 end=91")));
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let text = "Seed: not a number\n\
+                    Diversity: 100/100 distinct routes, mean edge distance 0.512\n\
+                    hello, world\n\
+                    Score: 0, Population: 0, Distance: 0, Cities: []\n\
+                    , ,\n\
+                    91, 2, 91\n\
+                    [91, 5,\n\
+                    6";
+        let routes = parse_routes(text);
+        // only the bare list is a route, the array is never closed and the empty ones carry no route
+        assert_eq!(indexes(&routes), vec![vec![91, 2, 91]]);
+        assert_eq!(routes[0].seed, None);
+        assert!(parse_routes("").is_empty());
+    }
+}
@@ -0,0 +1,437 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use csv::{ReaderBuilder, StringRecord};
+use rayon::prelude::*;
+
+use crate::calculations::{Cities, Distances, Path};
+use crate::data::DataError;
+use crate::geo;
+
+// highway values of OpenStreetMap ways a car can drive on, footways, paths, cycleways, steps and the like are left out
+const DRIVABLE_HIGHWAYS: [&str; 15] = [
+    "motorway", "trunk", "primary", "secondary", "tertiary", "unclassified", "residential", "service", "living_street", "road",
+    "motorway_link", "trunk_link", "primary_link", "secondary_link", "tertiary_link",
+];
+
+// road network: nodes with (longitude, latitude) and directed weighted edges in km
+#[derive(Clone)]
+pub struct RoadGraph {
+    pub coords: Vec<(f32, f32)>,
+    pub edges: Vec<Vec<(usize, f64)>>,
+}
+
+// result of routing every city to every other city over the road graph
+pub struct RoadRoutes {
+    pub city_nodes: Vec<usize>, // graph node closest to each city
+    pub distances: Distances,
+    legs: Vec<Vec<Vec<usize>>>, // legs[from][to] = node sequence of the shortest road
+}
+
+// drive_through[from][to] = cities whose road node lies on the leg from -> to, endpoints excluded
+pub type DriveThrough = Vec<Vec<Vec<usize>>>;
+
+pub struct RoadNetwork {
+    pub graph: RoadGraph,
+    pub routes: RoadRoutes,
+}
+
+impl RoadGraph {
+    fn add_edge(&mut self, from: usize, to: usize, length: f64, direction: Direction) {
+        if direction != Direction::Backward {
+            self.edges[from].push((to, length));
+        }
+        if direction != Direction::Forward {
+            self.edges[to].push((from, length));
+        }
+    }
+
+    pub fn nearest_node(&self, coords: (f32, f32)) -> Option<usize> {
+        (0..self.coords.len()).min_by(|&a, &b| {
+            geo::haversine_km(coords, self.coords[a]).total_cmp(&geo::haversine_km(coords, self.coords[b]))
+        })
+    }
+
+    pub fn polyline(&self, nodes: &[usize]) -> Vec<(f32, f32)> {
+        nodes.iter().map(|&node| self.coords[node]).collect()
+    }
+
+    // shortest distance (in meters, to keep the heap ordering exact) from source to every node,
+    // together with the predecessor of each node on that shortest path
+    fn dijkstra(&self, source: usize) -> (Vec<u64>, Vec<usize>) {
+        let mut distances = vec![u64::MAX; self.coords.len()];
+        let mut previous = vec![usize::MAX; self.coords.len()];
+        let mut heap = BinaryHeap::new();
+        distances[source] = 0;
+        heap.push(Reverse((0u64, source)));
+        while let Some(Reverse((distance, node))) = heap.pop() {
+            if distance > distances[node] {
+                continue; // stale heap entry
+            }
+            for &(neighbor, length) in &self.edges[node] {
+                let candidate = distance + (length * 1000.0).round() as u64;
+                if candidate < distances[neighbor] {
+                    distances[neighbor] = candidate;
+                    previous[neighbor] = node;
+                    heap.push(Reverse((candidate, neighbor)));
+                }
+            }
+        }
+        (distances, previous)
+    }
+}
+
+impl RoadRoutes {
+    pub fn leg(&self, from_city: usize, to_city: usize) -> &[usize] {
+        &self.legs[from_city][to_city]
+    }
+
+    // node sequence of every leg of the path, in order
+    pub fn path_legs(&self, path: &Path) -> Vec<&[usize]> {
+        path.city_indexes.windows(2).map(|pair| self.leg(pair[0], pair[1])).collect()
+    }
+
+    pub fn drive_through(&self) -> DriveThrough {
+        let mut cities_at_node: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (city, &node) in self.city_nodes.iter().enumerate() {
+            cities_at_node.entry(node).or_default().push(city);
+        }
+        self.legs.iter().enumerate().map(|(from, legs)| {
+            legs.iter().enumerate().map(|(to, leg)| {
+                leg.iter()
+                    .filter_map(|node| cities_at_node.get(node))
+                    .flatten()
+                    .copied()
+                    .filter(|&city| city != from && city != to)
+                    .collect()
+            }).collect()
+        }).collect()
+    }
+}
+
+impl RoadNetwork {
+    // coordinates along the road of every leg of the path, used for drawing and exporting
+    pub fn leg_polylines(&self, path: &Path) -> Vec<Vec<(f32, f32)>> {
+        self.routes.path_legs(path).iter().map(|leg| self.graph.polyline(leg)).collect()
+    }
+}
+
+fn read_records(filename: &str) -> Result<(StringRecord, Vec<StringRecord>), DataError> {
+    let mut rdr = ReaderBuilder::new().flexible(true).from_path(filename)
+        .map_err(|error| DataError::new(filename, None, None, error.to_string()))?;
+    let headers = rdr.headers().map_err(|error| DataError::new(filename, Some(1), None, error.to_string()))?.clone();
+    let mut records = Vec::new();
+    for (index, result) in rdr.records().enumerate() {
+        records.push(result.map_err(|error| DataError::new(filename, Some(index + 2), None, error.to_string()))?);
+    }
+    Ok((headers, records))
+}
+
+fn column(filename: &str, headers: &StringRecord, accepted: &[&str]) -> Result<usize, DataError> {
+    headers.iter().position(|header| accepted.contains(&header.trim().to_lowercase().as_str()))
+        .ok_or_else(|| DataError::new(filename, Some(1), None, format!("no column named one of {:?}", accepted)))
+}
+
+fn optional_column(headers: &StringRecord, accepted: &[&str]) -> Option<usize> {
+    headers.iter().position(|header| accepted.contains(&header.trim().to_lowercase().as_str()))
+}
+
+fn cell<T: std::str::FromStr>(filename: &str, record: &StringRecord, row: usize, column: usize) -> Result<T, DataError> {
+    let value = record.get(column).unwrap_or_default().trim();
+    value.parse().map_err(|_| DataError::new(filename, Some(row), Some(column + 1), format!("'{}' is not a valid number", value)))
+}
+
+// which way the traffic goes on an edge or a way, Backward for oneway=-1 (against the order of its nodes)
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    Both,
+    Forward,
+    Backward,
+}
+
+fn direction(oneway: &str) -> Direction {
+    match oneway.trim().to_lowercase().as_str() {
+        "1" | "yes" | "true" => Direction::Forward,
+        "-1" | "reverse" => Direction::Backward,
+        _ => Direction::Both,
+    }
+}
+
+// nodes file: id,lon,lat
+// edges file: from,to[,length_km][,oneway] - missing lengths are computed from the node coordinates, oneway -1 goes from to from
+pub fn load_road_csv(nodes_file: &str, edges_file: &str) -> Result<RoadGraph, DataError> {
+    let mut graph = RoadGraph { coords: Vec::new(), edges: Vec::new() };
+    let mut ids: HashMap<String, usize> = HashMap::new();
+
+    let (headers, records) = read_records(nodes_file)?;
+    let id_column = column(nodes_file, &headers, &["id", "node", "node_id"])?;
+    let lon_column = column(nodes_file, &headers, &["longitude", "lon", "lng", "x"])?;
+    let lat_column = column(nodes_file, &headers, &["latitude", "lat", "y"])?;
+    for (index, record) in records.iter().enumerate() {
+        let row = index + 2;
+        let id = record.get(id_column).unwrap_or_default().trim().to_string();
+        if ids.contains_key(&id) {
+            return Err(DataError::new(nodes_file, Some(row), Some(id_column + 1), format!("duplicate node id '{}'", id)));
+        }
+        let lon: f32 = cell(nodes_file, record, row, lon_column)?;
+        let lat: f32 = cell(nodes_file, record, row, lat_column)?;
+        ids.insert(id, graph.coords.len());
+        graph.coords.push((lon, lat));
+        graph.edges.push(Vec::new());
+    }
+
+    let (headers, records) = read_records(edges_file)?;
+    let from_column = column(edges_file, &headers, &["from", "source", "u"])?;
+    let to_column = column(edges_file, &headers, &["to", "target", "v"])?;
+    let length_column = optional_column(&headers, &["length", "length_km", "km", "distance"]);
+    let oneway_column = optional_column(&headers, &["oneway"]);
+    for (index, record) in records.iter().enumerate() {
+        let row = index + 2;
+        let mut endpoints = Vec::with_capacity(2);
+        for column in [from_column, to_column] {
+            let id = record.get(column).unwrap_or_default().trim();
+            match ids.get(id) {
+                Some(&node) => endpoints.push(node),
+                None => return Err(DataError::new(edges_file, Some(row), Some(column + 1), format!("unknown node id '{}'", id))),
+            }
+        }
+        let (from, to) = (endpoints[0], endpoints[1]);
+        let length = match length_column {
+            Some(column) if !record.get(column).unwrap_or_default().trim().is_empty() => cell(edges_file, record, row, column)?,
+            _ => geo::haversine_km(graph.coords[from], graph.coords[to]),
+        };
+        let direction = oneway_column.map_or(Direction::Both, |column| direction(record.get(column).unwrap_or_default()));
+        graph.add_edge(from, to, length, direction);
+    }
+    Ok(graph)
+}
+
+// value of attribute name in the tag text, e.g. attribute("node id=\"5\" lat=\"48.8\"", "lat") == Some("48.8")
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    for quote in ['"', '\''] {
+        let pattern = format!(" {}={}", name, quote);
+        if let Some(start) = tag.find(&pattern) {
+            let rest = &tag[start + pattern.len()..];
+            return rest.find(quote).map(|end| &rest[..end]);
+        }
+    }
+    None
+}
+
+// OpenStreetMap XML export (e.g. from osmium or overpass), only ways with a drivable highway tag become edges
+// the parser is deliberately simple: it walks the tags and ignores everything but node, way, nd and tag
+pub fn load_road_osm(filename: &str) -> Result<RoadGraph, DataError> {
+    let content = std::fs::read_to_string(filename).map_err(|error| DataError::new(filename, None, None, error.to_string()))?;
+
+    let mut node_coords: HashMap<&str, (f32, f32)> = HashMap::new();
+    let mut ways: Vec<(Vec<&str>, Direction)> = Vec::new();
+    let mut way_nodes: Vec<&str> = Vec::new();
+    let mut way_is_road = false;
+    let mut way_direction = Direction::Both;
+    let mut in_way = false;
+
+    for (line_index, line) in content.lines().enumerate() {
+        for tag in line.split('<').skip(1) {
+            let tag = tag.split('>').next().unwrap_or_default();
+            let tag = tag.trim_end_matches('/');
+            if tag.starts_with("node ") {
+                let (Some(id), Some(lat), Some(lon)) = (attribute(tag, "id"), attribute(tag, "lat"), attribute(tag, "lon")) else {
+                    return Err(DataError::new(filename, Some(line_index + 1), None, "node without id, lat or lon".to_string()));
+                };
+                let (Ok(lat), Ok(lon)) = (lat.parse::<f32>(), lon.parse::<f32>()) else {
+                    return Err(DataError::new(filename, Some(line_index + 1), None, format!("node {} has invalid coordinates", id)));
+                };
+                node_coords.insert(id, (lon, lat));
+            } else if tag.starts_with("way ") || tag == "way" {
+                in_way = true;
+                way_nodes.clear();
+                way_is_road = false;
+                way_direction = Direction::Both;
+            } else if in_way && tag.starts_with("nd ") {
+                if let Some(reference) = attribute(tag, "ref") {
+                    way_nodes.push(reference);
+                }
+            } else if in_way && tag.starts_with("tag ") {
+                match (attribute(tag, "k"), attribute(tag, "v")) {
+                    (Some("highway"), Some(value)) => way_is_road = DRIVABLE_HIGHWAYS.contains(&value),
+                    (Some("oneway"), Some(value)) => way_direction = direction(value),
+                    _ => {}
+                }
+            } else if tag == "/way" {
+                if way_is_road {
+                    ways.push((way_nodes.clone(), way_direction));
+                }
+                in_way = false;
+            }
+        }
+    }
+
+    // keep only nodes that are part of a road
+    let mut graph = RoadGraph { coords: Vec::new(), edges: Vec::new() };
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    for (nodes, direction) in &ways {
+        let mut previous: Option<usize> = None;
+        for id in nodes {
+            let Some(&coords) = node_coords.get(id) else {
+                previous = None; // the export was clipped, the way continues outside of it
+                continue;
+            };
+            let node = *ids.entry(id).or_insert_with(|| {
+                graph.coords.push(coords);
+                graph.edges.push(Vec::new());
+                graph.coords.len() - 1
+            });
+            if let Some(previous) = previous {
+                let length = geo::haversine_km(graph.coords[previous], coords);
+                graph.add_edge(previous, node, length, *direction);
+            }
+            previous = Some(node);
+        }
+    }
+    if graph.coords.is_empty() {
+        return Err(DataError::new(filename, None, None, "file contains no roads".to_string()));
+    }
+    Ok(graph)
+}
+
+// snaps every city to its closest node and runs Dijkstra from each of them
+// errors name the file the graph was loaded from
+pub fn route_cities(graph: &RoadGraph, cities: &Cities, filename: &str) -> Result<RoadRoutes, DataError> {
+    let city_nodes: Vec<usize> = cities.coords.iter()
+        .map(|&coords| graph.nearest_node(coords).ok_or_else(|| DataError::new(filename, None, None, "road graph has no nodes".to_string())))
+        .collect::<Result<_, _>>()?;
+
+    let results: Vec<(Vec<u32>, Vec<Vec<usize>>)> = city_nodes.par_iter().map(|&source| {
+        let (node_distances, previous) = graph.dijkstra(source);
+        let mut row = Vec::with_capacity(city_nodes.len());
+        let mut legs = Vec::with_capacity(city_nodes.len());
+        for &target in &city_nodes {
+            if node_distances[target] == u64::MAX {
+                row.push(u32::MAX);
+                legs.push(Vec::new());
+                continue;
+            }
+            row.push((node_distances[target] as f64 / 1000.0).round() as u32);
+            let mut leg = vec![target];
+            while *leg.last().unwrap() != source {
+                leg.push(previous[*leg.last().unwrap()]);
+            }
+            leg.reverse();
+            legs.push(leg);
+        }
+        (row, legs)
+    }).collect();
+
+    let mut unreachable = Vec::new();
+    for (from, (row, _)) in results.iter().enumerate() {
+        for (to, &distance) in row.iter().enumerate() {
+            if distance == u32::MAX {
+                unreachable.push(format!("{} -> {}", cities.names[from], cities.names[to]));
+            }
+        }
+    }
+    if !unreachable.is_empty() {
+        return Err(DataError::new(filename, None, None, format!("{} city pairs are not connected by roads, e.g. {}", unreachable.len(), unreachable[0])));
+    }
+
+    let (distances, legs) = results.into_iter().unzip();
+    Ok(RoadRoutes { city_nodes, distances, legs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculations::Grouping;
+
+    // writes the content to a file in the temp directory, named after the test so that tests running in parallel do not clash
+    fn temp_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("gelnica-chopper-roads-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn cities(coords: &[(f32, f32)]) -> Cities {
+        Cities {
+            names: (0..coords.len()).map(|city| format!("city{}", city)).collect(),
+            populations: vec![1000; coords.len()],
+            coords: coords.to_vec(),
+            overnight: vec![true; coords.len()],
+            values: vec![None; coords.len()],
+            regions: Grouping::default(),
+            districts: Grouping::default(),
+        }
+    }
+
+    // a - b - c along the road, and a long way round a - d - c
+    const NODES: &str = "id,lon,lat\na,21.0,48.0\nb,21.1,48.0\nc,21.2,48.0\nd,21.1,48.5\n";
+
+    #[test]
+    fn dijkstra_takes_the_shortest_road_between_cities() {
+        let edges = "from,to,length_km\na,b,10\nb,c,10\na,d,30\nd,c,30\n";
+        let graph = load_road_csv(&temp_file("shortest-nodes.csv", NODES), &temp_file("shortest-edges.csv", edges)).unwrap();
+        let routes = route_cities(&graph, &cities(&[(21.0, 48.0), (21.2, 48.0)]), "edges.csv").unwrap();
+        assert_eq!(routes.distances, vec![vec![0, 20], vec![20, 0]]);
+        assert_eq!(routes.leg(0, 1), &[0, 1, 2]);
+        assert_eq!(routes.leg(1, 0), &[2, 1, 0]);
+    }
+
+    #[test]
+    fn oneway_edges_are_driven_in_their_direction_only() {
+        // b -> c is one way, given as yes on b,c and as -1 on c,b, so c to a has to go round d
+        let edges = "from,to,length_km,oneway\na,b,10,no\nb,c,10,yes\na,d,30,\nd,c,30,\n";
+        let graph = load_road_csv(&temp_file("oneway-nodes.csv", NODES), &temp_file("oneway-edges.csv", edges)).unwrap();
+        let routes = route_cities(&graph, &cities(&[(21.0, 48.0), (21.2, 48.0)]), "edges.csv").unwrap();
+        assert_eq!(routes.distances, vec![vec![0, 20], vec![60, 0]]);
+
+        let edges = "from,to,length_km,oneway\na,b,10,no\nc,b,10,-1\na,d,30,\nd,c,30,\n";
+        let graph = load_road_csv(&temp_file("reversed-nodes.csv", NODES), &temp_file("reversed-edges.csv", edges)).unwrap();
+        let routes = route_cities(&graph, &cities(&[(21.0, 48.0), (21.2, 48.0)]), "edges.csv").unwrap();
+        assert_eq!(routes.distances, vec![vec![0, 20], vec![60, 0]]);
+    }
+
+    #[test]
+    fn cities_without_a_road_between_them_are_an_error() {
+        let edges = "from,to,length_km\na,b,10\nc,d,10\n";
+        let edges_file = temp_file("disconnected-edges.csv", edges);
+        let graph = load_road_csv(&temp_file("disconnected-nodes.csv", NODES), &edges_file).unwrap();
+        let error = route_cities(&graph, &cities(&[(21.0, 48.0), (21.2, 48.0)]), &edges_file).err().unwrap();
+        assert_eq!(error, DataError::new(&edges_file, None, None, "2 city pairs are not connected by roads, e.g. city0 -> city1".to_string()));
+    }
+
+    #[test]
+    fn osm_loader_keeps_drivable_ways_only() {
+        let osm = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="48.0" lon="21.0"/>
+  <node id="2" lat="48.0" lon="21.1"/>
+  <node id="3" lat="48.0" lon="21.2"/>
+  <node id="4" lat="48.1" lon="21.1"/>
+  <way id="10">
+    <nd ref="1"/>
+    <nd ref="2"/>
+    <tag k="highway" v="footway"/>
+  </way>
+  <way id="11">
+    <nd ref="1"/>
+    <nd ref="4"/>
+    <nd ref="3"/>
+    <tag k="highway" v="secondary"/>
+  </way>
+  <way id="12">
+    <nd ref="3"/>
+    <nd ref="2"/>
+    <tag k="highway" v="residential"/>
+    <tag k="oneway" v="-1"/>
+  </way>
+</osm>
+"#;
+        let graph = load_road_osm(&temp_file("drivable.osm", osm)).unwrap();
+        // the footway alone does not bring node 1 and 2 together, node 2 is only reached over the residential way
+        assert_eq!(graph.coords.len(), 4);
+        let node = |coords: (f32, f32)| graph.nearest_node(coords).unwrap();
+        let (start, middle, end) = (node((21.0, 48.0)), node((21.1, 48.0)), node((21.2, 48.0)));
+        assert!(graph.edges[start].iter().all(|&(to, _)| to != middle));
+        // oneway=-1 on 3 -> 2 lets the traffic go from 2 to 3 only
+        assert!(graph.edges[middle].iter().any(|&(to, _)| to == end));
+        assert!(graph.edges[end].iter().all(|&(to, _)| to != middle));
+    }
+}
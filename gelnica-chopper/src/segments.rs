@@ -0,0 +1,59 @@
+use std::collections::{HashMap, HashSet};
+
+// ordered run of cities shared by several routes
+#[derive(Clone, Debug)]
+pub struct Segment {
+    pub cities: Vec<usize>,
+    pub routes: Vec<usize>, // indexes of the routes containing the segment, in either direction
+}
+
+// a segment and its reverse are the same piece of road, the smaller one represents both
+fn canonical(segment: &[usize]) -> Vec<usize> {
+    let reversed: Vec<usize> = segment.iter().rev().copied().collect();
+    if reversed.as_slice() < segment { reversed } else { segment.to_vec() }
+}
+
+// finds every segment of at least min_length cities that appears in two or more routes,
+// ranked by the number of routes containing it and then by length
+// segments that only occur as a part of a longer segment found in the same routes are left out
+pub fn common_segments(routes: &[Vec<usize>], min_length: usize) -> Vec<Segment> {
+    let min_length = min_length.max(2);
+    let mut found: HashMap<Vec<usize>, Vec<usize>> = HashMap::new();
+    for (route_index, route) in routes.iter().enumerate() {
+        let mut seen_in_route: HashSet<Vec<usize>> = HashSet::new();
+        for start in 0..route.len() {
+            for end in (start + min_length)..=route.len() {
+                let segment = canonical(&route[start..end]);
+                if seen_in_route.insert(segment.clone()) {
+                    found.entry(segment).or_default().push(route_index);
+                }
+            }
+        }
+    }
+    found.retain(|_, containing| containing.len() >= 2);
+
+    // a segment is not maximal if it can be extended by one city without losing any route
+    let mut not_maximal: HashSet<Vec<usize>> = HashSet::new();
+    for (segment, containing) in &found {
+        if segment.len() <= min_length {
+            continue;
+        }
+        for shorter in [&segment[1..], &segment[..segment.len() - 1]] {
+            let shorter = canonical(shorter);
+            if found.get(&shorter).is_some_and(|other| other.len() == containing.len()) {
+                not_maximal.insert(shorter);
+            }
+        }
+    }
+
+    let mut segments: Vec<Segment> = found.into_iter()
+        .filter(|(segment, _)| !not_maximal.contains(segment))
+        .map(|(cities, routes)| Segment { cities, routes })
+        .collect();
+    segments.sort_by(|a, b| {
+        b.routes.len().cmp(&a.routes.len())
+            .then(b.cities.len().cmp(&a.cities.len()))
+            .then(a.cities.cmp(&b.cities))
+    });
+    segments
+}
@@ -0,0 +1,330 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::calculations::{self, Cities, Config, Distances, Path};
+use crate::diff::{self, RouteDiff};
+use crate::roads::RoadNetwork;
+use crate::sweep::{self, Step};
+
+// map projection shared by the live window and the SVG output
+pub const X_MIN: f32 = 17.0;
+pub const X_MAX: f32 = 22.5;
+pub const Y_MIN: f32 = 47.5;
+pub const Y_MAX: f32 = 49.5;
+
+// Screen size
+pub const SCREEN_WIDTH: f32 = 1800.0;
+pub const SCREEN_HEIGHT: f32 = 800.0;
+
+pub fn scale_x(x: f32) -> f32 {
+    ((x - X_MIN) / (X_MAX - X_MIN)) * SCREEN_WIDTH
+}
+
+pub fn scale_y(y: f32) -> f32 {
+    SCREEN_HEIGHT - ((y - Y_MIN) / (Y_MAX - Y_MIN)) * SCREEN_HEIGHT
+}
+
+// radius of the city marker, grows with population between 2000 and 200000 inhabitants
+fn marker_radius(population: u32) -> f32 {
+    let min_population = 2000.0;
+    let max_population = 200000.0;
+    let clamped_population = (population as f32).clamp(min_population, max_population);
+    let normalized = (clamped_population - min_population) / (max_population - min_population);
+    3.0 + normalized * 7.0
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// polyline of a single leg in screen coordinates
+fn leg_line(from: usize, to: usize, cities: &Cities, roads: Option<&RoadNetwork>) -> Vec<(f32, f32)> {
+    let line = match roads {
+        Some(roads) => roads.graph.polyline(roads.routes.leg(from, to)),
+        None => vec![cities.coords[from], cities.coords[to]],
+    };
+    line.into_iter().map(|(x, y)| (scale_x(x), scale_y(y))).collect()
+}
+
+// polyline of the route in screen coordinates, one entry per leg
+fn leg_points(path: &Path, cities: &Cities, roads: Option<&RoadNetwork>) -> Vec<Vec<(f32, f32)>> {
+    path.city_indexes.windows(2).map(|leg| leg_line(leg[0], leg[1], cities, roads)).collect()
+}
+
+fn write_polyline(svg: &mut String, points: &[(f32, f32)], attributes: &str) {
+    let points: Vec<String> = points.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect();
+    let _ = writeln!(svg, "<polyline points=\"{}\" {}/>", points.join(" "), attributes);
+}
+
+fn write_header(svg: &mut String) {
+    let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"sans-serif\">",
+        SCREEN_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH, SCREEN_HEIGHT);
+    svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+}
+
+fn write_legend(svg: &mut String, lines: &[String]) {
+    let height = 20.0 + 18.0 * lines.len() as f32;
+    let _ = writeln!(svg, "<rect x=\"10\" y=\"10\" width=\"330\" height=\"{:.1}\" fill=\"white\" fill-opacity=\"0.9\" stroke=\"black\"/>", height);
+    for (index, line) in lines.iter().enumerate() {
+        let _ = writeln!(svg, "<text x=\"20\" y=\"{:.1}\" font-size=\"14\">{}</text>", 30.0 + 18.0 * index as f32, escape(line));
+    }
+}
+
+// renders the whole map with the route, the output only depends on the inputs so it can be compared in tests
+pub fn render_route(path: &Path, cities: &Cities, distances: &Distances, config: &Config, roads: Option<&RoadNetwork>) -> String {
+    let visited: HashSet<usize> = path.city_indexes.iter().copied().collect();
+    let driven_through = calculations::driven_through(path, config);
+
+    let mut svg = String::new();
+    write_header(&mut svg);
+
+    // route first, so that the markers are drawn over it
+    svg.push_str("<g id=\"route\" fill=\"none\" stroke=\"black\" stroke-width=\"2\">\n");
+    let legs = leg_points(path, cities, roads);
+    for leg in &legs {
+        write_polyline(&mut svg, leg, "");
+    }
+    svg.push_str("</g>\n<g id=\"leg-distances\" font-size=\"9\" fill=\"dimgray\">\n");
+    for (leg, cities_pair) in legs.iter().zip(path.city_indexes.windows(2)) {
+        // label in the middle of the leg, a straight leg has no point there
+        let (x, y) = if leg.len() == 2 { ((leg[0].0 + leg[1].0) / 2.0, (leg[0].1 + leg[1].1) / 2.0) } else { leg[leg.len() / 2] };
+        let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\">{} km</text>", x + 2.0, y - 2.0, distances[cities_pair[0]][cities_pair[1]]);
+    }
+    svg.push_str("</g>\n");
+
+    svg.push_str("<g id=\"cities\">\n");
+    for (city, &(lon, lat)) in cities.coords.iter().enumerate() {
+        let (x, y) = (scale_x(lon), scale_y(lat));
+        let (fill, stroke) = if config.is_bad_city(city) {
+            (if visited.contains(&city) { "red" } else { "mistyrose" }, "red")
+        } else if visited.contains(&city) {
+            ("royalblue", "black")
+        } else if driven_through.contains(&city) {
+            ("limegreen", "black")
+        } else {
+            ("lightgray", "gray")
+        };
+        let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\" stroke=\"{}\"><title>{} {}</title></circle>",
+            x, y, marker_radius(cities.populations[city]), fill, stroke, city, escape(&cities.names[city]));
+        if visited.contains(&city) || driven_through.contains(&city) {
+            let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\">{}({})</text>", x + 5.0, y - 5.0, escape(&cities.names[city]), cities.populations[city]);
+        } else {
+            let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"8\" fill=\"gray\">{}</text>", x + 5.0, y - 5.0, escape(&cities.names[city]));
+        }
+    }
+    svg.push_str("</g>\n");
+
+    let mut legend = vec![
+        format!("Score: {}", path.score),
+        format!("Population: {}", path.population),
+        format!("Distance: {} km", path.distance),
+        format!("Stops: {}", path.city_indexes.len()),
+    ];
+    if !driven_through.is_empty() {
+        legend.push(format!("Driven through: {}", driven_through.len()));
+    }
+    if config.end != Some(config.start) {
+        let end = config.end.map_or("anywhere", |end| cities.names[end].as_str());
+        legend.push(format!("One-way: {} -> {}", cities.names[config.start], end));
+    }
+    legend.push("blue = visited, green = driven through, red = forbidden".to_string());
+    write_legend(&mut svg, &legend);
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// both routes on one map: shared legs in gray, legs only in A red, legs only in B green
+pub fn render_diff(a: &Path, b: &Path, route_diff: &RouteDiff, cities: &Cities, roads: Option<&RoadNetwork>) -> String {
+    let (legs_a, legs_b) = (diff::legs(a), diff::legs(b));
+    let mut svg = String::new();
+    write_header(&mut svg);
+
+    svg.push_str("<g id=\"legs\" fill=\"none\">\n");
+    let mut shared: Vec<&(usize, usize)> = legs_a.intersection(&legs_b).collect();
+    shared.sort();
+    for &(from, to) in shared {
+        write_polyline(&mut svg, &leg_line(from, to, cities, roads), "stroke=\"darkgray\" stroke-width=\"3\"");
+    }
+    for &(from, to) in &route_diff.legs_only_in_a {
+        write_polyline(&mut svg, &leg_line(from, to, cities, roads), "stroke=\"red\" stroke-width=\"2\" stroke-dasharray=\"6,3\"");
+    }
+    for &(from, to) in &route_diff.legs_only_in_b {
+        write_polyline(&mut svg, &leg_line(from, to, cities, roads), "stroke=\"green\" stroke-width=\"2\"");
+    }
+    svg.push_str("</g>\n");
+
+    let visited_a: HashSet<usize> = a.city_indexes.iter().copied().collect();
+    let visited_b: HashSet<usize> = b.city_indexes.iter().copied().collect();
+    svg.push_str("<g id=\"cities\">\n");
+    for (city, &(lon, lat)) in cities.coords.iter().enumerate() {
+        let (x, y) = (scale_x(lon), scale_y(lat));
+        let fill = match (visited_a.contains(&city), visited_b.contains(&city)) {
+            (true, true) if route_diff.reordered.contains(&city) => "orange",
+            (true, true) => "royalblue",
+            (true, false) => "red",
+            (false, true) => "limegreen",
+            (false, false) => "lightgray",
+        };
+        let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\" stroke=\"gray\"><title>{} {}</title></circle>",
+            x, y, marker_radius(cities.populations[city]), fill, city, escape(&cities.names[city]));
+        if visited_a.contains(&city) || visited_b.contains(&city) {
+            let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\">{}</text>", x + 5.0, y - 5.0, escape(&cities.names[city]));
+        }
+    }
+    svg.push_str("</g>\n");
+
+    write_legend(&mut svg, &[
+        format!("A: score {}, population {}, {} km", a.score, a.population, a.distance),
+        format!("B: score {}, population {}, {} km", b.score, b.population, b.distance),
+        format!("B - A: score {:+}, population {:+}, {:+} km", route_diff.score_change, route_diff.population_change, route_diff.distance_change),
+        "red = only A, green = only B, orange = reordered".to_string(),
+    ]);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// size of the charts, independent of the map
+const CHART_WIDTH: f32 = 900.0;
+const CHART_HEIGHT: f32 = 600.0;
+const CHART_MARGIN: f32 = 70.0;
+
+// empty chart with labelled axes, returns the function placing (x, y) values onto it
+fn write_chart_axes(svg: &mut String, x_range: (f32, f32), y_range: (f32, f32), x_label: &str, y_label: &str) -> impl Fn(f32, f32) -> (f32, f32) {
+    let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"sans-serif\">",
+        CHART_WIDTH, CHART_HEIGHT, CHART_WIDTH, CHART_HEIGHT);
+    svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+    let (x_min, x_max) = (x_range.0, x_range.1.max(x_range.0 + 1.0));
+    let (y_min, y_max) = (y_range.0, y_range.1.max(y_range.0 + 1.0));
+    let place = move |x: f32, y: f32| (
+        CHART_MARGIN + (x - x_min) / (x_max - x_min) * (CHART_WIDTH - 2.0 * CHART_MARGIN),
+        CHART_HEIGHT - CHART_MARGIN - (y - y_min) / (y_max - y_min) * (CHART_HEIGHT - 2.0 * CHART_MARGIN),
+    );
+
+    svg.push_str("<g id=\"axes\" stroke=\"black\" font-size=\"11\">\n");
+    write_polyline(svg, &[place(x_min, y_max), place(x_min, y_min), place(x_max, y_min)], "fill=\"none\"");
+    let ticks = 10;
+    for tick in 0..=ticks {
+        let x = x_min + (x_max - x_min) * tick as f32 / ticks as f32;
+        let (px, py) = place(x, y_min);
+        let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" stroke=\"none\" text-anchor=\"middle\">{:.0}</text>", px, py + 16.0, x);
+        let y = y_min + (y_max - y_min) * tick as f32 / ticks as f32;
+        let (px, py) = place(x_min, y);
+        let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" stroke=\"none\" text-anchor=\"end\">{:.0}</text>", px - 4.0, py + 4.0, y);
+    }
+    let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" stroke=\"none\" text-anchor=\"middle\" font-size=\"13\">{}</text>",
+        CHART_WIDTH / 2.0, CHART_HEIGHT - 20.0, escape(x_label));
+    let _ = writeln!(svg, "<text x=\"20\" y=\"{:.1}\" stroke=\"none\" text-anchor=\"middle\" font-size=\"13\" transform=\"rotate(-90 20 {:.1})\">{}</text>",
+        CHART_HEIGHT / 2.0, CHART_HEIGHT / 2.0, escape(y_label));
+    svg.push_str("</g>\n");
+    place
+}
+
+// best population over the distance budget, hovering a point lists the cities the budget added
+pub fn render_sweep(steps: &[Step], cities: &Cities) -> String {
+    let from = steps.first().map_or(0, |step| step.budget);
+    let to = steps.last().map_or(0, |step| step.budget);
+    let max_population = steps.iter().filter_map(|step| step.path.as_ref()).map(|path| path.population).max().unwrap_or(0);
+    let mut svg = String::new();
+    let place = write_chart_axes(&mut svg, (from as f32, to as f32), (0.0, max_population as f32), "distance budget (km)", "population");
+
+    // budgets without a route within them are left out of the curve and marked on the axis
+    let feasible: Vec<(&Step, &Path)> = steps.iter().filter_map(|step| Some((step, step.path.as_ref()?))).collect();
+    let points: Vec<(f32, f32)> = feasible.iter().map(|(step, path)| place(step.budget as f32, path.population as f32)).collect();
+    svg.push_str("<g id=\"curve\" fill=\"none\" stroke=\"royalblue\" stroke-width=\"2\">\n");
+    write_polyline(&mut svg, &points, "");
+    svg.push_str("</g>\n<g id=\"budgets\" fill=\"black\">\n");
+    for ((step, path), (x, y)) in feasible.iter().zip(&points) {
+        let added: Vec<&str> = step.added.iter().map(|&city| cities.names[city].as_str()).collect();
+        let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\"><title>{} km: {} inhabitants, {} km driven{}{}</title></circle>",
+            x, y, step.budget, path.population, path.distance, if added.is_empty() { "" } else { ", added " }, escape(&added.join(", ")));
+    }
+    for step in steps.iter().filter(|step| step.path.is_none()) {
+        let (x, y) = place(step.budget as f32, 0.0);
+        let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"white\" stroke=\"red\"><title>{} km: no route within the budget</title></circle>",
+            x, y, step.budget);
+    }
+    svg.push_str("</g>\n");
+
+    let mut legend = vec![format!("Budgets: {} to {} km", from, to)];
+    if let Some(per_100_km) = sweep::population_per_100_km(steps) {
+        legend.push(format!("Every extra 100 km: {:+.0} inhabitants on average", per_100_km));
+    }
+    write_legend(&mut svg, &legend);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// population over distance: every route of the front as a dot, the best route per budget as a staircase
+pub fn render_pareto(front: &[Path], budgets: &[(u32, Option<&Path>)]) -> String {
+    let max_distance = budgets.last().map_or(0, |(budget, _)| *budget).max(front.last().map_or(0, |path| path.distance));
+    let max_population = front.last().map_or(0, |path| path.population);
+    let mut svg = String::new();
+    let place = write_chart_axes(&mut svg, (0.0, max_distance as f32), (0.0, max_population as f32), "distance (km)", "population");
+
+    svg.push_str("<g id=\"budgets\" fill=\"none\" stroke=\"royalblue\" stroke-width=\"2\">\n");
+    let mut steps = Vec::new();
+    for (budget, path) in budgets {
+        if let Some(path) = path {
+            steps.push(place(*budget as f32, path.population as f32));
+        }
+    }
+    write_polyline(&mut svg, &steps, "");
+    svg.push_str("</g>\n<g id=\"front\" fill=\"black\">\n");
+    for path in front {
+        let (x, y) = place(path.distance as f32, path.population as f32);
+        let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2.5\"><title>{} km, {} inhabitants, {} stops</title></circle>",
+            x, y, path.distance, path.population, path.city_indexes.len());
+    }
+    svg.push_str("</g>\n");
+
+    write_legend(&mut svg, &[
+        format!("Routes on the front: {}", front.len()),
+        "dots = non-dominated routes, blue = best per budget".to_string(),
+    ]);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculations::Grouping;
+
+    // compares with the file in src/snapshots, UPDATE_SNAPSHOTS=1 cargo test writes the current output there instead
+    fn assert_snapshot(name: &str, expected: &str, actual: &str) {
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/snapshots").join(name);
+            std::fs::write(path, actual).unwrap();
+            return;
+        }
+        assert!(expected == actual, "{} differs from the snapshot, run UPDATE_SNAPSHOTS=1 cargo test and review the change", name);
+    }
+
+    #[test]
+    fn route_snapshot() {
+        let cities = Cities {
+            names: vec!["Gelnica".to_string(), "Košice".to_string(), "Prešov".to_string(), "Rožňava".to_string(), "A&B <town>".to_string()],
+            populations: vec![7448, 236563, 86138, 19000, 1500],
+            coords: vec![(20.937, 48.855), (21.258, 48.714), (21.240, 48.998), (20.531, 48.660), (20.700, 49.100)],
+            overnight: vec![true; 5],
+            values: vec![None; 5],
+            regions: Grouping::default(),
+            districts: Grouping::default(),
+        };
+        let distances = vec![
+            vec![0, 27, 20, 32, 25],
+            vec![27, 0, 32, 54, 48],
+            vec![20, 32, 0, 55, 40],
+            vec![32, 54, 55, 0, 48],
+            vec![25, 48, 40, 48, 0],
+        ];
+        let config = Config { start: 0, end: Some(0), bad_cities: vec![3], ..Config::default() };
+        let mut path = Path { city_indexes: vec![0, 1, 2, 0], population: 0, distance: 0, score: 0 };
+        calculations::calculate_score(&mut path, &cities, &distances, &config);
+
+        let svg = render_route(&path, &cities, &distances, &config, None);
+        assert_snapshot("route.svg", include_str!("snapshots/route.svg"), &svg);
+    }
+}
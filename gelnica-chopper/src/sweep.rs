@@ -0,0 +1,156 @@
+use std::error::Error;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use csv::Writer;
+
+use crate::calculations::{self, Cities, Config, Distances, Path};
+
+// budgets swept by default, in km
+pub const SWEEP_FROM: u32 = 1000;
+pub const SWEEP_TO: u32 = 2000;
+pub const SWEEP_STEP: u32 = 50;
+// iterations per budget, the generation carries over so later budgets need fewer than a fresh run
+pub const SWEEP_ITERATIONS: usize = 20000;
+
+// best route of a single budget and how it differs from the route of the previous budget that has one
+pub struct Step {
+    pub budget: u32,
+    pub path: Option<Path>,  // None when no route of the generation keeps within the budget
+    pub added: Vec<usize>,   // visited cities that were not visited with the previous budget
+    pub removed: Vec<usize>, // and the ones that had to go to make room for them
+}
+
+impl Step {
+    // population gained over the previous route, negative when the search got worse
+    pub fn gained(&self, previous: Option<&Path>) -> Option<i64> {
+        let path = self.path.as_ref()?;
+        Some(i64::from(path.population) - previous.map_or(0, |previous| i64::from(previous.population)))
+    }
+}
+
+// route of the last step before index that has one
+pub fn previous_path(steps: &[Step], index: usize) -> Option<&Path> {
+    steps[..index].iter().rev().find_map(|step| step.path.as_ref())
+}
+
+// from..=to by step
+pub fn budgets(from: u32, to: u32, step: u32) -> Vec<u32> {
+    (from..=to).step_by(step.max(1) as usize).collect()
+}
+
+// the route from the start to the end without the stops that save the most km for the least value,
+// until it keeps within the budget, the last stop of a route that may end anywhere stays as well
+fn trimmed(path: &Path, cities: &Cities, distances: &Distances, config: &Config, budget: u32) -> Path {
+    let mut path = path.clone();
+    if path.city_indexes.first() != Some(&config.start) {
+        path.city_indexes.insert(0, config.start);
+    }
+    if let Some(end) = config.end.filter(|&end| path.city_indexes.last() != Some(&end)) {
+        path.city_indexes.push(end);
+    }
+    calculations::calculate_score(&mut path, cities, distances, config);
+    while path.distance > budget && path.city_indexes.len() > 2 {
+        let stops = &path.city_indexes;
+        let cheapest = (1..stops.len() - 1)
+            .map(|index| {
+                let (before, city, after) = (stops[index - 1], stops[index], stops[index + 1]);
+                let saved = (distances[before][city] + distances[city][after]).saturating_sub(distances[before][after]);
+                let visited_again = stops.iter().filter(|&&stop| stop == city).count() > 1;
+                let lost = if visited_again { 0 } else { config.value.city_value(cities, city) };
+                (index, f64::from(lost) / f64::from(saved.max(1)))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((index, _)) = cheapest else {
+            break;
+        };
+        path.city_indexes.remove(index);
+        calculations::calculate_score(&mut path, cities, distances, config);
+    }
+    path
+}
+
+// optimizes for every budget in turn, each budget starts from the whole generation of the previous one
+// so the budgets should grow, then the best route of a budget is still valid for the next one
+// the distance limit is only a penalty for the search, so the reported route is the best one that keeps within the budget
+// and starts and ends where it should: from the generation, the trimmed best of the generation or the previous route
+pub fn sweep(cities: &Cities, distances: &Distances, config: &Config, budgets: &[u32], iterations: usize, seed: u64) -> Vec<Step> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut config = config.clone();
+    config.max_distance = budgets.first().copied();
+
+    println!("Seed: {}", seed);
+    println!("Config: {}", config);
+    println!("Sweeping the distance budget over {} values..", budgets.len());
+    let mut generation = calculations::initial_generation(cities, distances, &config, &mut rng);
+    let mut steps: Vec<Step> = Vec::new();
+    for &budget in budgets {
+        config.max_distance = Some(budget);
+        calculations::do_x_iterations(&mut generation, cities, distances, &config, iterations, &mut rng);
+        let feasible = |path: &Path| path.distance <= budget && path.city_indexes.first() == Some(&config.start)
+            && config.end.is_none_or(|end| path.city_indexes.last() == Some(&end));
+        let in_generation = generation.paths.iter().find(|path| feasible(path)).cloned();
+        let trimmed = Some(trimmed(&generation.paths[0], cities, distances, &config, budget)).filter(feasible);
+        let kept = previous_path(&steps, steps.len()).cloned().map(|mut path| {
+            calculations::calculate_score(&mut path, cities, distances, &config);
+            path
+        }).filter(feasible);
+        let Some(best) = in_generation.into_iter().chain(trimmed).chain(kept).max_by_key(|path| path.score) else {
+            println!("{:>5} km: no route within the budget", budget);
+            steps.push(Step { budget, path: None, added: Vec::new(), removed: Vec::new() });
+            continue;
+        };
+        let previous = previous_path(&steps, steps.len());
+        let visited = calculations::visited_cities(&best, &config);
+        let (added, removed) = match previous {
+            Some(previous) => {
+                let before = calculations::visited_cities(previous, &config);
+                let mut added: Vec<usize> = visited.difference(&before).copied().collect();
+                let mut removed: Vec<usize> = before.difference(&visited).copied().collect();
+                added.sort_unstable();
+                removed.sort_unstable();
+                (added, removed)
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+        let current = Step { budget, path: Some(best), added, removed };
+        if let (Some(path), Some(gained)) = (&current.path, current.gained(previous)) {
+            println!("{:>5} km: population {:>8} ({:+}), distance {:>5}, stops {:>3}", budget, path.population,
+                gained, path.distance, path.city_indexes.len());
+        }
+        steps.push(current);
+    }
+    steps
+}
+
+fn names(cities: &Cities, indexes: &[usize]) -> String {
+    let names: Vec<&str> = indexes.iter().map(|&city| cities.names[city].as_str()).collect();
+    names.join(", ")
+}
+
+// one line per budget: budget, population, gain over the previous budget, distance, stops, added and removed cities, the route
+// a budget without a route keeps only the budget, like pareto::write_csv
+pub fn write_csv(filename: &str, steps: &[Step], cities: &Cities) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_path(filename)?;
+    wtr.write_record(["budget", "population", "gained", "distance", "stops", "added", "removed", "cities"])?;
+    for (index, step) in steps.iter().enumerate() {
+        let record = match (&step.path, step.gained(previous_path(steps, index))) {
+            (Some(path), Some(gained)) => {
+                let indexes: Vec<String> = path.city_indexes.iter().map(|city| city.to_string()).collect();
+                [step.budget.to_string(), path.population.to_string(), gained.to_string(), path.distance.to_string(),
+                    path.city_indexes.len().to_string(), names(cities, &step.added), names(cities, &step.removed), indexes.join(", ")]
+            }
+            _ => [step.budget.to_string(), String::new(), String::new(), String::new(), String::new(), String::new(), String::new(), String::new()],
+        };
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// what every extra 100 km brings on average between the first and the last budget with a route
+pub fn population_per_100_km(steps: &[Step]) -> Option<f64> {
+    let mut feasible = steps.iter().filter_map(|step| Some((step.budget, step.path.as_ref()?)));
+    let (first_budget, first) = feasible.next()?;
+    let (last_budget, last) = feasible.next_back()?;
+    Some((f64::from(last.population) - f64::from(first.population)) * 100.0 / f64::from(last_budget - first_budget))
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+
+use crate::calculations::Distances;
+
+pub const DEFAULT_SPEED: f64 = 60.0;
+pub const DEFAULT_DWELL: u32 = 15;
+pub const DEFAULT_START: u32 = 8 * 60;
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+// when a stop is reached, all in minutes
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Visit {
+    pub arrival: u32, // after the start of the route
+    pub wait: u32,    // until the city opens
+    pub late: u32,    // after the city closed, such a visit is not possible
+}
+
+// how long a route takes: driving either at an average speed or by a matrix of minutes, plus a stop in every visited city
+#[derive(Clone)]
+pub struct TimeModel {
+    pub speed: f64,                 // km/h on every leg
+    pub matrix: Option<Distances>,  // driving minutes between cities, replaces the speed
+    pub dwell: u32,                 // minutes spent in every city between the start and the end
+    pub budget: Option<u32>,        // minutes available for the whole route
+    pub start: u32,                 // time of day when the route starts, minutes after midnight
+    pub windows: Vec<Option<(u32, u32)>>, // opening hours of every city, minutes after midnight, empty when there are none
+}
+
+impl Default for TimeModel {
+    fn default() -> Self {
+        TimeModel { speed: DEFAULT_SPEED, matrix: None, dwell: DEFAULT_DWELL, budget: None, start: DEFAULT_START, windows: Vec::new() }
+    }
+}
+
+impl fmt::Display for TimeModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.matrix {
+            Some(_) => write!(f, "matrix")?,
+            None => write!(f, "speed={}", self.speed)?,
+        }
+        write!(f, ",dwell={},budget={},start={},windows={}", self.dwell, self.budget.map_or("-".to_string(), format_minutes),
+            format_minutes(self.start), self.windows.iter().flatten().count())
+    }
+}
+
+impl TimeModel {
+    // driving minutes of a single leg
+    pub fn leg(&self, from: usize, to: usize, distances: &Distances) -> u32 {
+        match &self.matrix {
+            Some(matrix) => matrix[from][to],
+            None => (f64::from(distances[from][to]) * 60.0 / self.speed).round() as u32,
+        }
+    }
+
+    // arrival at every stop, waiting for the city to open when it is reached too early
+    // the windows repeat every day, the start of the route is not checked
+    pub fn visits(&self, stops: &[usize], distances: &Distances) -> Vec<Visit> {
+        let mut visits = Vec::with_capacity(stops.len());
+        let mut departure = 0;
+        for (order, &city) in stops.iter().enumerate() {
+            if order == 0 {
+                visits.push(Visit::default());
+                continue;
+            }
+            let arrival = departure + self.leg(stops[order - 1], city, distances);
+            let mut visit = Visit { arrival, wait: 0, late: 0 };
+            if let Some(Some((open, close))) = self.windows.get(city) {
+                let time_of_day = (self.start + arrival) % MINUTES_PER_DAY;
+                if time_of_day < *open {
+                    visit.wait = open - time_of_day;
+                } else if time_of_day > *close {
+                    visit.late = time_of_day - close;
+                }
+            }
+            departure = arrival + visit.wait + self.dwell;
+            visits.push(visit);
+        }
+        visits
+    }
+
+    // minutes over the budget plus the minutes of every visit after closing time, 0 for a route that keeps to the time
+    pub fn violation(&self, stops: &[usize], distances: &Distances) -> u32 {
+        let visits = self.visits(stops, distances);
+        let over = match (self.budget, visits.last()) {
+            (Some(budget), Some(end)) => end.arrival.saturating_sub(budget),
+            _ => 0,
+        };
+        over + visits.iter().map(|visit| visit.late).sum::<u32>()
+    }
+}
+
+// 0:00 style, hours can go over 24
+pub fn format_minutes(minutes: u32) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+// "12" or "11:30" hours
+pub fn parse_hours(text: &str) -> Result<u32, String> {
+    let invalid = || format!("'{}' is not a number of hours, e.g. 12 or 11:30", text);
+    match text.split_once(':') {
+        Some((hours, minutes)) => {
+            let hours: u32 = hours.trim().parse().map_err(|_| invalid())?;
+            let minutes: u32 = minutes.trim().parse().map_err(|_| invalid())?;
+            if minutes >= 60 {
+                return Err(invalid());
+            }
+            Ok(hours * 60 + minutes)
+        }
+        None => {
+            let hours: f64 = text.trim().parse().map_err(|_| invalid())?;
+            if hours < 0.0 {
+                return Err(invalid());
+            }
+            Ok((hours * 60.0).round() as u32)
+        }
+    }
+}
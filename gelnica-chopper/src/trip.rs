@@ -0,0 +1,61 @@
+use crate::calculations::{Cities, Distances, Path};
+
+// one day of a multi-day trip, start and end are positions in the path
+// the end of a day is the start of the next one
+#[derive(Clone, Debug)]
+pub struct Day {
+    pub start: usize,
+    pub end: usize,
+    pub distance: u32,
+    pub limit: u32,
+}
+
+// "400,350,400" or "3x400"
+pub fn parse_day_limits(text: &str) -> Result<Vec<u32>, String> {
+    if let Some((count, limit)) = text.split_once('x') {
+        let count: usize = count.trim().parse().map_err(|_| format!("'{}' is not a number of days", count))?;
+        let limit: u32 = limit.trim().parse().map_err(|_| format!("'{}' is not a distance", limit))?;
+        return if count == 0 { Err("at least one day is needed".to_string()) } else { Ok(vec![limit; count]) };
+    }
+    text.split(',')
+        .map(|limit| limit.trim().parse().map_err(|_| format!("'{}' is not a distance", limit)))
+        .collect()
+}
+
+// splits the path into at most limits.len() days, each day drives as far as it can and stops in an overnight city
+// a day that cannot reach any overnight city within its limit goes on to the first one, the last day always ends at the end of the path
+pub fn split_days(path: &Path, cities: &Cities, distances: &Distances, limits: &[u32]) -> Vec<Day> {
+    let stops = &path.city_indexes;
+    if stops.len() < 2 || limits.is_empty() {
+        return Vec::new();
+    }
+    // km driven from the start to every stop
+    let mut driven = vec![0; stops.len()];
+    for i in 1..stops.len() {
+        driven[i] = driven[i - 1] + distances[stops[i - 1]][stops[i]];
+    }
+    let last = stops.len() - 1;
+    let can_end = |position: usize| position == last || cities.overnight[stops[position]];
+
+    let mut days = Vec::new();
+    let mut start = 0;
+    for (day, &limit) in limits.iter().enumerate() {
+        if start == last {
+            break;
+        }
+        let end = if day == limits.len() - 1 {
+            last
+        } else {
+            (start + 1..=last).rev().find(|&position| can_end(position) && driven[position] - driven[start] <= limit)
+                .unwrap_or_else(|| (start + 1..=last).find(|&position| can_end(position)).unwrap())
+        };
+        days.push(Day { start, end, distance: driven[end] - driven[start], limit });
+        start = end;
+    }
+    days
+}
+
+// km driven over the daily limits
+pub fn excess(days: &[Day]) -> u32 {
+    days.iter().map(|day| day.distance.saturating_sub(day.limit)).sum()
+}
@@ -0,0 +1,131 @@
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rayon::prelude::*;
+
+use crate::calculations::{self, Cities, Config, Distances};
+use crate::params::{GaParams, CROSSOVERS};
+
+pub const TUNE_CANDIDATES: usize = 16;
+pub const TUNE_ROUNDS: usize = 3;
+pub const TUNE_SEEDS: usize = 4;
+// paths scored by a single run in the first round, doubled every round
+// counting scored paths instead of iterations keeps big generations from getting more work for free
+pub const TUNE_EVALUATIONS: usize = 200000;
+
+// 97.5% quantile of the t distribution for 1 to 30 degrees of freedom, the normal one above that
+const T_QUANTILES: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160, 2.145, 2.131,
+    2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+const Z_QUANTILE: f64 = 1.96;
+
+// how much work the search gets
+#[derive(Clone, Copy, Debug)]
+pub struct Budget {
+    pub candidates: usize, // random configurations in the first round, the current one included
+    pub rounds: usize,     // every round keeps the better half
+    pub seeds: usize,      // runs per configuration and round
+    pub evaluations: usize,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget { candidates: TUNE_CANDIDATES, rounds: TUNE_ROUNDS, seeds: TUNE_SEEDS, evaluations: TUNE_EVALUATIONS }
+    }
+}
+
+// a configuration and the best scores of its runs in the last round it took part in
+#[derive(Clone, Debug)]
+pub struct Trial {
+    pub params: GaParams,
+    pub scores: Vec<u32>,
+}
+
+impl Trial {
+    pub fn mean(&self) -> f64 {
+        self.scores.iter().map(|&score| f64::from(score)).sum::<f64>() / self.scores.len().max(1) as f64
+    }
+
+    // half-width of the 95% confidence interval of the mean, 0 for a single run
+    pub fn interval(&self) -> f64 {
+        let count = self.scores.len();
+        if count < 2 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        let variance = self.scores.iter().map(|&score| (f64::from(score) - mean).powi(2)).sum::<f64>() / (count - 1) as f64;
+        let quantile = T_QUANTILES.get(count - 2).copied().unwrap_or(Z_QUANTILE);
+        quantile * (variance / count as f64).sqrt()
+    }
+}
+
+// a random configuration around the hand-picked one
+fn sample(rng: &mut StdRng) -> GaParams {
+    let boundary_elite = rng.gen_range(5..=30);
+    let boundary_mutate = (boundary_elite + rng.gen_range(5..=40)).min(95);
+    let boundary_random = (boundary_mutate + rng.gen_range(0..=30)).min(100);
+    GaParams {
+        generation_size: rng.gen_range(5..=30) * 10,
+        boundary_elite,
+        boundary_mutate,
+        boundary_random,
+        chance_firstcity: rng.gen_range(0..=20),
+        chance_lastcity: rng.gen_range(0..=20),
+        chance_addone: rng.gen_range(10..=90),
+        chance_removeone: rng.gen_range(10..=90),
+        chance_reorderone: rng.gen_range(0..=60),
+        crossover: *CROSSOVERS.choose(rng).unwrap(),
+    }
+}
+
+// score of the best path of a single short run
+fn run(params: GaParams, cities: &Cities, distances: &Distances, config: &Config, evaluations: usize, seed: u64) -> u32 {
+    let config = Config { ga: params, ..config.clone() };
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut generation = calculations::initial_generation(cities, distances, &config, &mut rng);
+    let iterations = (evaluations / params.generation_size).max(1);
+    calculations::do_x_iterations(&mut generation, cities, distances, &config, iterations, &mut rng).score
+}
+
+// successive halving over random configurations: every round runs the remaining ones on the same fresh seeds
+// with twice the evaluations of the round before, then keeps the better half
+// returns the configurations of the last round, best mean first
+pub fn tune(cities: &Cities, distances: &Distances, config: &Config, budget: Budget, seed: u64) -> Vec<Trial> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    println!("Seed: {}", seed);
+    println!("Config: {}", config);
+    let mut trials: Vec<Trial> = std::iter::once(config.ga)
+        .chain((1..budget.candidates.max(1)).map(|_| sample(&mut rng)))
+        .map(|params| Trial { params, scores: Vec::new() })
+        .collect();
+
+    for round in 0..budget.rounds.max(1) {
+        let evaluations = budget.evaluations << round;
+        let seeds: Vec<u64> = (0..budget.seeds.max(1)).map(|_| rng.gen()).collect();
+        println!("Round {}: {} configurations, {} runs of {} evaluated paths each..", round + 1, trials.len(), seeds.len(), evaluations);
+        let runs: Vec<(usize, u64)> = (0..trials.len()).flat_map(|trial| seeds.iter().map(move |&seed| (trial, seed))).collect();
+        let scores: Vec<u32> = runs.par_iter()
+            .map(|&(trial, seed)| run(trials[trial].params, cities, distances, config, evaluations, seed))
+            .collect();
+        for (trial, chunk) in trials.iter_mut().zip(scores.chunks(seeds.len())) {
+            trial.scores = chunk.to_vec();
+        }
+        trials.sort_by(|a, b| b.mean().total_cmp(&a.mean()));
+        for trial in &trials {
+            println!("  {:>10.0} ± {:>8.0}  {}", trial.mean(), trial.interval(), trial.params);
+        }
+        if round + 1 < budget.rounds {
+            trials.truncate(trials.len().div_ceil(2));
+        }
+    }
+    trials
+}
+
+// params file of the best configuration, the comments say how good it was
+pub fn file_text(best: &Trial, budget: Budget, seed: u64) -> String {
+    format!("# tuned with seed {}, {} candidates, {} rounds\n# mean best score {:.0} ± {:.0} (95%) over {} runs\n{}",
+        seed, budget.candidates, budget.rounds, best.mean(), best.interval(), best.scores.len(), best.params.file_text())
+}
@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::calculations::Cities;
+
+pub const DEFAULT_CAP: u32 = 50000;
+// log and sqrt values are scaled so that a town of about 10000 people is worth roughly its population
+const LOG_SCALE: f64 = 1000.0;
+const SQRT_SCALE: f64 = 100.0;
+
+// what a visited city is worth
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ValueModel {
+    #[default]
+    Raw,         // the population
+    Log,         // ln(1 + population), big cities stop dominating
+    Sqrt,        // sqrt(population), somewhere between raw and log
+    Capped(u32), // population, but at most the cap
+    Custom,      // the value column of cities.csv
+}
+
+// every model, in the order reports show them
+pub const MODELS: [ValueModel; 5] = [ValueModel::Raw, ValueModel::Log, ValueModel::Sqrt, ValueModel::Capped(DEFAULT_CAP), ValueModel::Custom];
+
+impl fmt::Display for ValueModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueModel::Raw => write!(f, "raw"),
+            ValueModel::Log => write!(f, "log"),
+            ValueModel::Sqrt => write!(f, "sqrt"),
+            ValueModel::Capped(cap) => write!(f, "capped:{}", cap),
+            ValueModel::Custom => write!(f, "custom"),
+        }
+    }
+}
+
+impl ValueModel {
+    // raw, log, sqrt, capped (at DEFAULT_CAP), capped:<cap> or custom
+    pub fn parse(text: &str) -> Result<ValueModel, String> {
+        match text.split_once(':') {
+            Some(("capped", cap)) => cap.parse().map(ValueModel::Capped).map_err(|_| format!("'{}' is not a population cap", cap)),
+            None => match text {
+                "raw" => Ok(ValueModel::Raw),
+                "log" => Ok(ValueModel::Log),
+                "sqrt" => Ok(ValueModel::Sqrt),
+                "capped" => Ok(ValueModel::Capped(DEFAULT_CAP)),
+                "custom" => Ok(ValueModel::Custom),
+                _ => Err(format!("unknown value model '{}', use raw, log, sqrt, capped[:cap] or custom", text)),
+            },
+            _ => Err(format!("unknown value model '{}', use raw, log, sqrt, capped[:cap] or custom", text)),
+        }
+    }
+
+    // a city without a custom value is worth nothing in the custom model
+    pub fn city_value(&self, cities: &Cities, city: usize) -> u32 {
+        let population = cities.populations[city];
+        match self {
+            ValueModel::Raw => population,
+            ValueModel::Log => (f64::from(population).ln_1p() * LOG_SCALE).round() as u32,
+            ValueModel::Sqrt => (f64::from(population).sqrt() * SQRT_SCALE).round() as u32,
+            ValueModel::Capped(cap) => population.min(*cap),
+            ValueModel::Custom => cities.values.get(city).copied().flatten().unwrap_or(0),
+        }
+    }
+
+    pub fn route_value(&self, cities: &Cities, visited: &HashSet<usize>) -> u32 {
+        visited.iter().map(|&city| self.city_value(cities, city)).sum()
+    }
+}